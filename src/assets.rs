@@ -0,0 +1,76 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::Path,
+};
+
+/// Content hashes of the files served under `/assets`, used for cache busting.
+#[derive(Debug, Default, Clone)]
+pub struct AssetManifest {
+    hashes: HashMap<String, String>,
+}
+
+impl AssetManifest {
+    /// Hashes every file directly inside `dir`.
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut hashes = HashMap::new();
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+
+            if !entry.file_type()?.is_file() {
+                continue;
+            }
+
+            let mut hasher = DefaultHasher::new();
+            fs::read(entry.path())?.hash(&mut hasher);
+
+            let name = entry.file_name().to_string_lossy().into_owned();
+            hashes.insert(name, format!("{:016x}", hasher.finish()));
+        }
+
+        Ok(Self { hashes })
+    }
+
+    /// The URL of an asset, fingerprinted with its hash when one is known.
+    pub fn url(&self, name: &str) -> String {
+        match self.hashes.get(name) {
+            Some(hash) => format!("/assets/{}?v={}", name, hash),
+            None => format!("/assets/{}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url() {
+        // Arrange
+        let manifest = AssetManifest::from_dir("assets").unwrap();
+        let hash = &manifest.hashes["helpers.js"];
+
+        // Act
+        let fingerprinted = manifest.url("helpers.js");
+        let unknown = manifest.url("missing.js");
+
+        // Assert
+        assert_eq!(fingerprinted, format!("/assets/helpers.js?v={}", hash));
+        assert_eq!(unknown, "/assets/missing.js");
+    }
+
+    #[test]
+    fn test_url_empty_manifest() {
+        // Arrange
+        let manifest = AssetManifest::default();
+
+        // Act
+        let result = manifest.url("helpers.js");
+
+        // Assert
+        assert_eq!(result, "/assets/helpers.js");
+    }
+}
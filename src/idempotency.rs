@@ -0,0 +1,112 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+};
+
+use uuid::Uuid;
+
+/// A key as sent by one client, so clients picking the same key do not see each other's todos.
+/// Clients whose address is unknown share a scope, like in `RateLimiter`.
+type ScopedKey = (Option<IpAddr>, String);
+
+/// Remembers which todo each recent `Idempotency-Key` created, per client address.
+///
+/// Once full, the least recently used key is forgotten first.
+#[derive(Debug, Clone)]
+pub struct IdempotencyKeys {
+    capacity: usize,
+    ids: HashMap<ScopedKey, Uuid>,
+    /// Keys from least to most recently used.
+    order: VecDeque<ScopedKey>,
+}
+
+impl IdempotencyKeys {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ids: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Returns the todo `client` created under `key`, marking the key as recently used.
+    pub fn get(&mut self, client: Option<IpAddr>, key: &str) -> Option<Uuid> {
+        let key = (client, key.to_owned());
+        let id = *self.ids.get(&key)?;
+        self.touch(&key);
+
+        Some(id)
+    }
+
+    /// Records that `client` created the todo `id` under `key`.
+    pub fn insert(&mut self, client: Option<IpAddr>, key: &str, id: Uuid) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let key = (client, key.to_owned());
+
+        if self.ids.insert(key.clone(), id).is_some() {
+            self.touch(&key);
+            return;
+        }
+
+        self.order.push_back(key);
+
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+    }
+
+    fn touch(&mut self, key: &ScopedKey) {
+        if let Some(index) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(index).unwrap();
+            self.order.push_back(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        // Arrange
+        let mut keys = IdempotencyKeys::new(2);
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        // Act
+        keys.insert(None, "a", a);
+        keys.insert(None, "b", b);
+        let got_a = keys.get(None, "a");
+        keys.insert(None, "c", c);
+
+        // Assert
+        assert_eq!(got_a, Some(a));
+        assert_eq!(keys.get(None, "a"), Some(a));
+        assert_eq!(keys.get(None, "b"), None);
+        assert_eq!(keys.get(None, "c"), Some(c));
+    }
+
+    #[test]
+    fn test_scoped_per_client() {
+        // Arrange
+        let mut keys = IdempotencyKeys::new(2);
+        let client = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let other = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        let id = Uuid::new_v4();
+
+        // Act
+        keys.insert(client, "a", id);
+
+        // Assert
+        assert_eq!(keys.get(client, "a"), Some(id));
+        assert_eq!(keys.get(other, "a"), None);
+        assert_eq!(keys.get(None, "a"), None);
+    }
+}
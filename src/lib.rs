@@ -4,51 +4,369 @@
 #![allow(clippy::unused_async)]
 #![allow(non_snake_case)]
 
+pub mod assets;
+pub mod idempotency;
 pub mod models;
+pub mod rate_limit;
 pub mod repository;
+pub mod util;
+pub mod ws;
 
 use askama::Template;
+use assets::AssetManifest;
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
-    routing::get,
-    Form, Router,
+    async_trait,
+    body::{Body, HttpBody},
+    extract::{ConnectInfo, DefaultBodyLimit, FromRequest, FromRequestParts, Path, Query, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{delete, get, patch, post},
+    Extension, Form, Json, Router,
 };
-use models::Todo;
-use serde::Deserialize;
+use idempotency::IdempotencyKeys;
+use models::{
+    is_valid_description, normalize_tag, parse_color, parse_tags, QueryParams, Recurrence, Todo,
+    TodoCursor, TodoUpdate, UnknownFilter, COLORS, MAX_DESCRIPTION_LEN, MAX_TEXT_LEN,
+};
+use rate_limit::RateLimiter;
+use serde::{de::IntoDeserializer, Deserialize, Serialize};
 use std::{
+    collections::hash_map::DefaultHasher,
+    convert::Infallible,
+    fmt::{self, Write},
+    future::Future,
+    hash::{Hash, Hasher},
     net::SocketAddr,
-    sync::{Arc, RwLock},
+    num::NonZeroUsize,
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, TryLockResult,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+use tokio::{
+    sync::{broadcast, watch},
+    time::MissedTickBehavior,
 };
-use tower_http::{services::ServeDir, trace::TraceLayer};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tower_cookies::{Cookie, CookieManagerLayer, Cookies, Key};
+use tower_http::{
+    compression::{
+        predicate::{DefaultPredicate, NotForContentType, Predicate},
+        CompressionLayer,
+    },
+    cors::CorsLayer,
+    services::ServeDir,
+    set_header::SetResponseHeader,
+    trace::TraceLayer,
+};
+use tracing::Span;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 use uuid::Uuid;
 
-use crate::models::{TodoListFilter, TodoToggleAction};
-use crate::repository::{TodoRepo, TodoRepoError};
+use crate::models::{
+    TodoDedupKeep, TodoEvent, TodoListFilter, TodoSort, TodoSource, TodoStatus, TodoToggleAction,
+};
+use crate::repository::{TodoPage, TodoRepo, TodoRepoError, UpdateOutcome};
 
 #[derive(Debug)]
 pub struct AppState {
-    pub selected_filter: TodoListFilter,
-    pub toggle_action: TodoToggleAction,
     pub todo_repo: TodoRepo,
+    pub events: broadcast::Sender<TodoEvent>,
+    /// Bearer token required by the `/admin` routes, which are disabled when unset.
+    pub admin_token: Option<String>,
+    /// Signs the cookie holding each client's selected filter.
+    pub cookie_key: CookieKey,
+    /// Fingerprints asset URLs; left empty, URLs are served unversioned.
+    pub assets: AssetManifest,
+    /// Directory served under `/assets`; read when the router is built.
+    pub assets_dir: PathBuf,
+    /// How long browsers may cache `/assets` responses; read when the router is built.
+    pub assets_max_age: Duration,
+    /// Limits mutating `/todo` requests per client; unlimited when unset.
+    pub write_limiter: Option<RateLimiter>,
+    /// Prefix every route is served under, e.g. "/todos"; read when the router is built.
+    pub base_path: String,
+    /// Running totals exported by `/metrics`, bumped alongside the change they count, under the
+    /// same write lock.
+    pub metrics: Metrics,
+    /// Most todos rendered in one list; the rest are only mentioned in a notice.
+    pub render_limit: usize,
+    /// Filter the index opens on and clients without a selected filter fall back to.
+    pub default_filter: TodoListFilter,
+    /// Origins allowed to call the routes cross-origin; read when the router is built.
+    pub cors_origins: Vec<HeaderValue>,
+    /// Largest request body in bytes the routes read; read when the router is built.
+    pub body_limit: usize,
+    /// How long completed todos are kept before the periodic task removes them; kept forever
+    /// when unset.
+    pub purge_completed_after: Option<Duration>,
+    /// Todos recently created under an `Idempotency-Key`, so a retried create is not repeated.
+    pub idempotency_keys: IdempotencyKeys,
+    /// Longest a request waits for the state lock before answering `503`; read when the router
+    /// is built.
+    pub lock_timeout: Duration,
+    /// Set through `/admin/readonly` during maintenance, turning away every `/todo` write.
+    pub read_only: AtomicBool,
+    /// Whether checking a todo through `update_todo` fires `COMPLETED_TRIGGER`.
+    pub completion_trigger: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub todos_created: AtomicU64,
+    pub todos_completed: AtomicU64,
+    pub todos_deleted: AtomicU64,
+}
+
+impl Metrics {
+    fn add(counter: &AtomicU64, count: usize) {
+        counter.fetch_add(count as u64, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone)]
+pub struct CookieKey(pub Key);
+
+impl fmt::Debug for CookieKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CookieKey(..)")
+    }
+}
+
+impl AppState {
+    /// Truncates `items` to the render limit, returning them with the untruncated count.
+    fn capped(&self, mut items: Vec<Todo>) -> (Vec<Todo>, usize) {
+        let num_listed = items.len();
+        items.truncate(self.render_limit);
+
+        (items, num_listed)
+    }
+
+    fn roll_recurring(&mut self, now: SystemTime) -> Vec<Todo> {
+        let rolled = self.todo_repo.roll_recurring(now);
+        Metrics::add(&self.metrics.todos_created, rolled.len());
+
+        for todo in &rolled {
+            self.publish(TodoEvent::Created(todo.id));
+        }
+
+        rolled
+    }
+
+    pub fn publish(&self, event: TodoEvent) {
+        // Sending only fails when nobody is subscribed, which is fine.
+        let _ = self.events.send(event);
+    }
 }
 
 impl Default for AppState {
     fn default() -> Self {
+        let (events, _) = broadcast::channel(EVENTS_CAPACITY);
+
         Self {
-            selected_filter: TodoListFilter::All,
-            toggle_action: TodoToggleAction::Check,
             todo_repo: TodoRepo::default(),
+            events,
+            admin_token: None,
+            cookie_key: CookieKey(Key::generate()),
+            assets: AssetManifest::default(),
+            assets_dir: PathBuf::from(DEFAULT_ASSETS_DIR),
+            assets_max_age: DEFAULT_ASSETS_MAX_AGE,
+            write_limiter: None,
+            base_path: String::new(),
+            metrics: Metrics::default(),
+            render_limit: DEFAULT_RENDER_LIMIT,
+            default_filter: TodoListFilter::All,
+            cors_origins: Vec::new(),
+            body_limit: DEFAULT_BODY_LIMIT,
+            purge_completed_after: None,
+            idempotency_keys: IdempotencyKeys::new(IDEMPOTENCY_KEYS_CAPACITY),
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            read_only: AtomicBool::new(false),
+            completion_trigger: false,
         }
     }
 }
 
 pub type SharedState = Arc<RwLock<AppState>>;
 
+/// Locks the state for reading, carrying on with whatever a panicking writer left behind.
+///
+/// The lock stays poisoned, so `/ready` keeps reporting the panic.
+pub fn read_state(shared_state: &SharedState) -> RwLockReadGuard<'_, AppState> {
+    shared_state.read().unwrap_or_else(|poisoned| {
+        tracing::warn!("using state poisoned by a panic");
+        poisoned.into_inner()
+    })
+}
+
+/// Locks the state for writing; see `read_state`.
+pub fn write_state(shared_state: &SharedState) -> RwLockWriteGuard<'_, AppState> {
+    shared_state.write().unwrap_or_else(|poisoned| {
+        tracing::warn!("using state poisoned by a panic");
+        poisoned.into_inner()
+    })
+}
+
+/// The shared state as request handlers lock it: giving up with `503` after `timeout`, e.g.
+/// behind a deadlocked handler, instead of waiting forever on a lock that is never released.
+///
+/// Requests first pass `gate` in arrival order, readers together and writers alone, so only
+/// locks taken outside a request compete for the state lock itself and a waiting writer is
+/// not starved by a stream of readers.
+#[derive(Debug, Clone)]
+struct StateLock {
+    shared_state: SharedState,
+    gate: Arc<tokio::sync::RwLock<()>>,
+    timeout: Duration,
+}
+
+/// The state as locked by `StateLock`, released together with its place in the gate.
+struct Locked<G, A> {
+    guard: G,
+    _admission: A,
+}
+
+type ReadLocked<'a> = Locked<RwLockReadGuard<'a, AppState>, tokio::sync::RwLockReadGuard<'a, ()>>;
+type WriteLocked<'a> =
+    Locked<RwLockWriteGuard<'a, AppState>, tokio::sync::RwLockWriteGuard<'a, ()>>;
+
+impl<G: Deref<Target = AppState>, A> Deref for Locked<G, A> {
+    type Target = AppState;
+
+    fn deref(&self) -> &AppState {
+        &self.guard
+    }
+}
+
+impl<G: DerefMut<Target = AppState>, A> DerefMut for Locked<G, A> {
+    fn deref_mut(&mut self) -> &mut AppState {
+        &mut self.guard
+    }
+}
+
+impl StateLock {
+    fn new(shared_state: SharedState, timeout: Duration) -> Self {
+        Self {
+            shared_state,
+            gate: Arc::default(),
+            timeout,
+        }
+    }
+
+    /// Locks the state for reading like `read_state`, held until the result is dropped.
+    async fn read(&self) -> Result<ReadLocked<'_>, AppError> {
+        let deadline = Instant::now() + self.timeout;
+        let admission = self.admit(self.gate.read()).await?;
+        let guard = locked(|| self.shared_state.try_read(), deadline, self.timeout).await?;
+
+        Ok(Locked {
+            guard,
+            _admission: admission,
+        })
+    }
+
+    /// Locks the state for writing like `write_state`, held until the result is dropped.
+    async fn write(&self) -> Result<WriteLocked<'_>, AppError> {
+        let deadline = Instant::now() + self.timeout;
+        let admission = self.admit(self.gate.write()).await?;
+        let guard = locked(|| self.shared_state.try_write(), deadline, self.timeout).await?;
+
+        Ok(Locked {
+            guard,
+            _admission: admission,
+        })
+    }
+
+    async fn admit<A>(&self, admission: impl Future<Output = A>) -> Result<A, AppError> {
+        tokio::time::timeout(self.timeout, admission)
+            .await
+            .map_err(|_| lock_timed_out(self.timeout))
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for StateLock {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<Self>()
+            .cloned()
+            .expect("`app` adds the state lock to every request"))
+    }
+}
+
+/// Polls `try_lock` until `deadline`; requests already take turns at the gate, so this only
+/// waits on locks held outside a request.
+async fn locked<G>(
+    try_lock: impl Fn() -> TryLockResult<G>,
+    deadline: Instant,
+    timeout: Duration,
+) -> Result<G, AppError> {
+    loop {
+        match try_lock() {
+            Ok(guard) => return Ok(guard),
+            Err(TryLockError::Poisoned(poisoned)) => {
+                tracing::warn!("using state poisoned by a panic");
+                return Ok(poisoned.into_inner());
+            }
+            Err(TryLockError::WouldBlock) if Instant::now() >= deadline => {
+                return Err(lock_timed_out(timeout));
+            }
+            Err(TryLockError::WouldBlock) => {}
+        }
+
+        tokio::time::sleep(LOCK_POLL_INTERVAL).await;
+    }
+}
+
+fn lock_timed_out(timeout: Duration) -> AppError {
+    tracing::error!("state lock still held after {:?}", timeout);
+    AppError::LockTimeout
+}
+
+const EVENTS_CAPACITY: usize = 64;
+const SELECTED_FILTER_HEADER: &str = "x-selected-filter";
+const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const IDEMPOTENCY_KEYS_CAPACITY: usize = 1024;
+const HX_TRIGGER_HEADER: &str = "hx-trigger";
+/// Fired when an edit moves a todo out of the selected filter, so the client can animate it away.
+const LEFT_FILTER_TRIGGER: &str = "todo-left-filter";
+/// Fired when the new todo form creates a todo, so the client can keep typing the next one.
+const CREATED_TRIGGER: &str = "todo-created";
+/// Fired when an edit checks an open todo, e.g. for the client to announce it to screen readers.
+const COMPLETED_TRIGGER: &str = "todo-completed";
+const SELECTED_FILTER_COOKIE: &str = "todo_filter";
+const VELOCITY_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+const WRITE_RATE_WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_ROLL_INTERVAL: Duration = Duration::from_secs(60);
+const DEFAULT_RENDER_LIMIT: usize = 500;
+const DEFAULT_BODY_LIMIT: usize = 64 * 1024;
+const DEFAULT_ASSETS_DIR: &str = "assets";
+const DEFAULT_ASSETS_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 enum AppError {
     TodoRepo(TodoRepoError),
+    InvalidMoveTarget,
+    PreconditionFailed,
+    MissingConfirmation,
+    InvalidId,
+    InvalidDescription,
+    SearchTooLong,
+    LockTimeout,
+    ReadOnly,
 }
 
 impl From<TodoRepoError> for AppError {
@@ -57,20 +375,265 @@ impl From<TodoRepoError> for AppError {
     }
 }
 
+#[derive(Template)]
+#[template(path = "responses/not_found.html")]
+struct NotFoundResponse {
+    message: String,
+}
+
+#[derive(Template)]
+#[template(path = "responses/bad_request.html")]
+struct BadRequestResponse {
+    message: String,
+}
+
+/// An error as sent to clients whose `Accept` asks for JSON.
+#[derive(Debug, Clone, Serialize)]
+struct ErrorResponse {
+    error: String,
+    /// Machine-readable, e.g. `not_found`.
+    code: &'static str,
+}
+
+impl AppError {
+    /// The status, machine-readable code and message the error is answered with.
+    fn describe(&self) -> (StatusCode, &'static str, String) {
+        // No wildcard arms, so a new `TodoRepoError` variant fails to compile until mapped here.
+        match self {
+            Self::TodoRepo(TodoRepoError::NotFound) => (
+                StatusCode::NOT_FOUND,
+                "not_found",
+                String::from("Todo not found"),
+            ),
+            Self::TodoRepo(TodoRepoError::Duplicate) => (
+                StatusCode::CONFLICT,
+                "duplicate",
+                String::from("An active todo with this text already exists"),
+            ),
+            Self::TodoRepo(TodoRepoError::CapacityReached) => (
+                StatusCode::INSUFFICIENT_STORAGE,
+                "capacity_reached",
+                String::from("The todo list is full"),
+            ),
+            Self::TodoRepo(TodoRepoError::Conflict) => (
+                StatusCode::CONFLICT,
+                "conflict",
+                String::from("Todo has changed since its revision was read"),
+            ),
+            Self::TodoRepo(TodoRepoError::BlankText) => (
+                StatusCode::BAD_REQUEST,
+                "blank_text",
+                String::from("Todo text must not be blank"),
+            ),
+            Self::TodoRepo(TodoRepoError::TextTooLong) => (
+                StatusCode::BAD_REQUEST,
+                "text_too_long",
+                format!("Todo texts are limited to {} characters", MAX_TEXT_LEN),
+            ),
+            Self::TodoRepo(TodoRepoError::InvalidTag) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_tag",
+                String::from("A tag must not contain commas"),
+            ),
+            Self::TodoRepo(TodoRepoError::InvalidColor) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_color",
+                format!("Color must be one of {}", COLORS.join(", ")),
+            ),
+            Self::InvalidMoveTarget => (
+                StatusCode::BAD_REQUEST,
+                "invalid_move_target",
+                String::from("Todos can only be moved to Active or Completed"),
+            ),
+            Self::PreconditionFailed => (
+                StatusCode::PRECONDITION_FAILED,
+                "precondition_failed",
+                String::from("Todo has changed since it was fetched"),
+            ),
+            Self::InvalidId => (
+                StatusCode::BAD_REQUEST,
+                "invalid_id",
+                String::from("Todo ids must be UUIDs"),
+            ),
+            Self::InvalidDescription => (
+                StatusCode::BAD_REQUEST,
+                "invalid_description",
+                format!(
+                    "Todo descriptions are limited to {} characters",
+                    MAX_DESCRIPTION_LEN
+                ),
+            ),
+            Self::SearchTooLong => (
+                StatusCode::BAD_REQUEST,
+                "search_too_long",
+                format!("Searches are limited to {} characters", MAX_TEXT_LEN),
+            ),
+            Self::MissingConfirmation => (
+                StatusCode::BAD_REQUEST,
+                "missing_confirmation",
+                String::from("Pass confirm=true to delete every todo"),
+            ),
+            Self::LockTimeout => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "lock_timeout",
+                String::from("The server is busy, try again later"),
+            ),
+            Self::ReadOnly => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "read_only",
+                String::from("Todos are read-only during maintenance"),
+            ),
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            Self::TodoRepo(TodoRepoError::NotFound) => (StatusCode::NOT_FOUND, "Todo not found"),
+        let (status, code, message) = self.describe();
+        let error = ErrorResponse {
+            error: message.clone(),
+            code,
+        };
+
+        let mut response = match self {
+            Self::TodoRepo(TodoRepoError::NotFound) => {
+                (status, NotFoundResponse { message }).into_response()
+            }
+            Self::InvalidId => (status, BadRequestResponse { message }).into_response(),
+            _ => (status, message).into_response(),
         };
 
-        (status, message).into_response()
+        // Picked up by `json_errors` for JSON clients.
+        response.extensions_mut().insert(error);
+        response
+    }
+}
+
+/// A todo id path param, rejecting malformed UUIDs with a styled fragment. Under `/t` the
+/// param is a `Todo::short_id` instead, resolved to the todo's id.
+struct TodoId(Uuid);
+
+#[derive(Debug, Deserialize)]
+struct TodoIdParams {
+    id: Option<Uuid>,
+    short_id: Option<String>,
+}
+
+#[async_trait]
+impl FromRequestParts<SharedState> for TodoId {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &SharedState,
+    ) -> Result<Self, Self::Rejection> {
+        let Path(params) = Path::<TodoIdParams>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| AppError::InvalidId)?;
+
+        match params {
+            TodoIdParams { id: Some(id), .. } => Ok(Self(id)),
+            TodoIdParams {
+                short_id: Some(short_id),
+                ..
+            } => {
+                let state_lock = StateLock::from_request_parts(parts, state).await?;
+                let id = state_lock
+                    .read()
+                    .await?
+                    .todo_repo
+                    .get_by_short(&short_id)?
+                    .id;
+
+                Ok(Self(id))
+            }
+            TodoIdParams { .. } => Err(AppError::InvalidId),
+        }
+    }
+}
+
+/// A todo id and one of its subtask ids, rejected like `TodoId`.
+struct SubtaskId(Uuid, Uuid);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for SubtaskId {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Path::<(Uuid, Uuid)>::from_request_parts(parts, state)
+            .await
+            .map(|Path((id, subtask_id))| Self(id, subtask_id))
+            .map_err(|_| AppError::InvalidId)
+    }
+}
+
+/// Texts for `POST /todo/batch`, either a JSON array or the newline-separated `lines` form
+/// field, with the source that format implies.
+struct BatchLines(Vec<String>, TodoSource);
+
+#[derive(Debug, Deserialize)]
+struct BatchLinesForm {
+    lines: String,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S, Body> for BatchLines {
+    type Rejection = Response;
+
+    async fn from_request(request: Request<Body>, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+
+        if is_json {
+            Json::<Vec<String>>::from_request(request, state)
+                .await
+                .map(|Json(lines)| Self(lines, TodoSource::Api))
+                .map_err(IntoResponse::into_response)
+        } else {
+            Form::<BatchLinesForm>::from_request(request, state)
+                .await
+                .map(|Form(BatchLinesForm { lines })| {
+                    Self(lines.lines().map(String::from).collect(), TodoSource::Web)
+                })
+                .map_err(IntoResponse::into_response)
+        }
     }
 }
 
 pub fn app(shared_state: SharedState) -> Router {
-    Router::new()
-        .nest_service("/assets", ServeDir::new("assets"))
+    // The base path is plain config, so a poisoned lock must not keep `/ready` from answering.
+    let (base_path, cors_origins, body_limit, assets_dir, assets_max_age, lock_timeout) = {
+        let state = shared_state.read().unwrap_or_else(PoisonError::into_inner);
+        (
+            state.base_path.clone(),
+            state.cors_origins.clone(),
+            state.body_limit,
+            state.assets_dir.clone(),
+            state.assets_max_age,
+            state.lock_timeout,
+        )
+    };
+    let state_lock = StateLock::new(shared_state.clone(), lock_timeout);
+    let admin = Router::new()
+        .route("/admin/deduplicate", post(deduplicate_todos))
+        .route("/admin/readonly", post(set_read_only))
+        .route_layer(middleware::from_fn_with_state(
+            state_lock.clone(),
+            require_admin,
+        ));
+    // Only the static files are cacheable; every `/todo` response reflects the current list.
+    let assets = SetResponseHeader::if_not_present(
+        ServeDir::new(assets_dir),
+        header::CACHE_CONTROL,
+        HeaderValue::from_str(&format!("public, max-age={}", assets_max_age.as_secs())).unwrap(),
+    );
+    let routes = Router::new()
+        .nest_service("/assets", assets)
         .route("/", get(get_index))
+        .route("/openapi.json", get(get_openapi))
         .route(
             "/todo",
             get(list_todos)
@@ -78,42 +641,657 @@ pub fn app(shared_state: SharedState) -> Router {
                 .patch(toggle_completed_todos)
                 .delete(delete_completed_todos),
         )
+        .route("/todo/all", delete(clear_todos))
+        .route("/todo/batch", post(create_todos_batch))
+        .route("/todo/batch/complete", patch(set_completed_todos))
+        .route("/todo/completed/preview", get(preview_completed_todos))
+        .route("/todo/completed/reset", patch(reset_completed_todos))
+        .route("/todo/count", get(count_todos))
+        .route("/todo/events", get(stream_todo_events))
+        .route("/todo/export", get(export_todos))
+        .route("/todo/grouped", get(grouped_todos))
+        .route("/todo/import", post(import_todos))
+        .route("/todo/matrix", get(todo_matrix))
+        .route("/todo/roll", post(roll_recurring_todos))
+        .route("/todo/summary", get(todo_summary))
+        .route("/todo/tags/add", post(add_tag_to_todos))
+        .route("/todo/tags/remove", post(remove_tag_from_todos))
+        .route("/todo/toggle/reset", post(reset_toggle_button))
+        .route("/todo/transaction", post(apply_transaction))
+        .route("/todo/trash", delete(purge_todos))
+        .route("/todo/version", get(todo_version))
+        .route("/todo/ws", get(ws::todo_socket))
         .route(
             "/todo/:id",
             get(edit_todo).patch(update_todo).delete(delete_todo),
         )
-        .layer(TraceLayer::new_for_http())
-        .with_state(shared_state)
+        .route("/todo/:id/append", patch(append_todo_text))
+        .route("/todo/:id/complete", patch(complete_todo))
+        .route("/todo/:id/duplicate", post(duplicate_todo))
+        .route("/todo/:id/incomplete", patch(incomplete_todo))
+        .route("/todo/:id/move-to-filter", post(move_todo_to_filter))
+        .route("/todo/:id/position", patch(reorder_todo))
+        .route("/todo/:id/restore", post(restore_todo))
+        .route("/todo/:id/subtask", post(add_subtask))
+        .route(
+            "/todo/:id/subtask/:subtask_id",
+            patch(toggle_subtask).delete(remove_subtask),
+        )
+        .route("/todo/:id/view", get(view_todo))
+        .route(
+            "/t/:short_id",
+            get(edit_todo).patch(update_todo).delete(delete_todo),
+        )
+        .merge(admin)
+        // Body extractors stop reading past the limit and answer `413 Payload Too Large`.
+        .layer(DefaultBodyLimit::max(body_limit))
+        .layer(middleware::from_fn_with_state(
+            state_lock.clone(),
+            limit_writes,
+        ))
+        // Outside `limit_writes`, so turned away writes do not count towards the rate limit.
+        .layer(middleware::from_fn_with_state(
+            state_lock.clone(),
+            reject_writes_when_read_only,
+        ))
+        .layer(middleware::from_fn(json_errors))
+        .layer(CookieManagerLayer::new())
+        // Server-sent events must reach the client as they are written, so they stay uncompressed.
+        .layer(CompressionLayer::new().compress_when(
+            DefaultPredicate::new().and(NotForContentType::const_new("text/event-stream")),
+        ))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|request: &Request<Body>| {
+                    tracing::debug_span!(
+                        "request",
+                        method = %request.method(),
+                        path = request.uri().path(),
+                        status = tracing::field::Empty,
+                    )
+                })
+                .on_response(|response: &Response, latency: Duration, span: &Span| {
+                    span.record("status", response.status().as_u16());
+                    tracing::debug!(?latency, "finished processing request");
+                }),
+        )
+        .route("/metrics", get(get_metrics))
+        .route("/health", get(get_health))
+        .route("/ready", get(get_ready))
+        .layer(Extension(state_lock));
+
+    let routes = if base_path.is_empty() {
+        routes
+    } else {
+        Router::new().nest(&base_path, routes)
+    };
+
+    // Without configured origins browsers keep enforcing the same-origin policy.
+    let routes = if cors_origins.is_empty() {
+        routes
+    } else {
+        routes.layer(
+            CorsLayer::new()
+                .allow_origin(cors_origins)
+                .allow_methods([
+                    Method::GET,
+                    Method::POST,
+                    Method::PUT,
+                    Method::PATCH,
+                    Method::DELETE,
+                ])
+                .allow_headers([
+                    header::CONTENT_TYPE,
+                    header::IF_MATCH,
+                    header::IF_NONE_MATCH,
+                    header::HeaderName::from_static(IDEMPOTENCY_KEY_HEADER),
+                ])
+                .expose_headers([
+                    header::ETAG,
+                    header::HeaderName::from_static(SELECTED_FILTER_HEADER),
+                    header::HeaderName::from_static(NEXT_CURSOR_HEADER),
+                ]),
+        )
+    };
+
+    routes.with_state(shared_state)
 }
 
 pub async fn run() {
+    let is_json = std::env::var("TODO_LOG_FORMAT").as_deref() == Ok("json");
+
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "todomvc=debug,tower_http=debug".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(is_json.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!is_json).then(tracing_subscriber::fmt::layer))
         .init();
 
     let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
     tracing::debug!("listening on {}", addr);
 
     let shared_state = SharedState::default();
+    write_state(&shared_state).admin_token = std::env::var("TODO_ADMIN_TOKEN").ok();
+
+    if let Ok(base_path) = std::env::var("TODO_BASE_PATH") {
+        write_state(&shared_state).base_path = base_path.trim_end_matches('/').to_string();
+    }
+
+    match std::env::var("TODO_CAPACITY").map(|capacity| capacity.parse::<usize>()) {
+        Ok(Ok(capacity)) => {
+            write_state(&shared_state).todo_repo = TodoRepo::with_capacity(capacity);
+        }
+        Ok(Err(err)) => tracing::warn!("ignoring TODO_CAPACITY: {}", err),
+        Err(_) => {}
+    }
+
+    if std::env::var("TODO_NORMALIZE_TEXT").as_deref() == Ok("off") {
+        write_state(&shared_state).todo_repo.normalize = false;
+    }
+
+    if std::env::var("TODO_AUTO_COMPLETE_SUBTASKS").as_deref() == Ok("off") {
+        write_state(&shared_state).todo_repo.auto_complete = false;
+    }
+
+    if std::env::var("TODO_COMPLETION_TRIGGER").as_deref() == Ok("on") {
+        write_state(&shared_state).completion_trigger = true;
+    }
+
+    if let Ok(sort) = std::env::var("TODO_DEFAULT_SORT") {
+        let sort: Result<TodoSort, serde::de::value::Error> =
+            TodoSort::deserialize(sort.as_str().into_deserializer());
+
+        match sort {
+            Ok(sort) => write_state(&shared_state).todo_repo.default_sort = sort,
+            Err(err) => tracing::warn!("ignoring TODO_DEFAULT_SORT: {}", err),
+        }
+    }
+
+    if let Ok(assets_dir) = std::env::var("TODO_ASSETS_DIR") {
+        write_state(&shared_state).assets_dir = PathBuf::from(assets_dir);
+    }
+
+    match std::env::var("TODO_ASSETS_MAX_AGE_SECS").map(|secs| secs.parse::<u64>()) {
+        Ok(Ok(secs)) => write_state(&shared_state).assets_max_age = Duration::from_secs(secs),
+        Ok(Err(err)) => tracing::warn!("ignoring TODO_ASSETS_MAX_AGE_SECS: {}", err),
+        Err(_) => {}
+    }
+
+    if std::env::var("TODO_ASSET_FINGERPRINTING").as_deref() != Ok("off") {
+        let assets_dir = read_state(&shared_state).assets_dir.clone();
+
+        match AssetManifest::from_dir(assets_dir) {
+            Ok(assets) => write_state(&shared_state).assets = assets,
+            Err(err) => tracing::warn!("asset fingerprinting disabled: {}", err),
+        }
+    }
+
+    match std::env::var("TODO_WRITE_RATE_LIMIT").map(|limit| limit.parse::<u32>()) {
+        Ok(Ok(limit)) => {
+            write_state(&shared_state).write_limiter =
+                Some(RateLimiter::new(limit, WRITE_RATE_WINDOW));
+        }
+        Ok(Err(err)) => tracing::warn!("ignoring TODO_WRITE_RATE_LIMIT: {}", err),
+        Err(_) => {}
+    }
+
+    if let Ok(origins) = std::env::var("TODO_CORS_ORIGINS") {
+        let origins = origins
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .filter_map(|origin| match HeaderValue::from_str(origin) {
+                Ok(origin) => Some(origin),
+                Err(err) => {
+                    tracing::warn!("ignoring TODO_CORS_ORIGINS entry {:?}: {}", origin, err);
+                    None
+                }
+            })
+            .collect();
+
+        write_state(&shared_state).cors_origins = origins;
+    }
+
+    match std::env::var("TODO_DEFAULT_FILTER").map(|filter| filter.parse::<TodoListFilter>()) {
+        Ok(Ok(filter)) => write_state(&shared_state).default_filter = filter,
+        Ok(Err(UnknownFilter(filter))) => {
+            tracing::warn!("ignoring TODO_DEFAULT_FILTER: unknown filter {:?}", filter);
+        }
+        Err(_) => {}
+    }
+
+    match std::env::var("TODO_BODY_LIMIT").map(|limit| limit.parse::<usize>()) {
+        Ok(Ok(limit)) => write_state(&shared_state).body_limit = limit,
+        Ok(Err(err)) => tracing::warn!("ignoring TODO_BODY_LIMIT: {}", err),
+        Err(_) => {}
+    }
+
+    match std::env::var("TODO_LOCK_TIMEOUT_MS").map(|ms| ms.parse::<u64>()) {
+        Ok(Ok(ms)) => write_state(&shared_state).lock_timeout = Duration::from_millis(ms),
+        Ok(Err(err)) => tracing::warn!("ignoring TODO_LOCK_TIMEOUT_MS: {}", err),
+        Err(_) => {}
+    }
+
+    match std::env::var("TODO_RENDER_LIMIT").map(|limit| limit.parse::<usize>()) {
+        Ok(Ok(limit)) => write_state(&shared_state).render_limit = limit,
+        Ok(Err(err)) => tracing::warn!("ignoring TODO_RENDER_LIMIT: {}", err),
+        Err(_) => {}
+    }
+
+    match std::env::var("TODO_PURGE_COMPLETED_AFTER_DAYS").map(|days| days.parse::<u64>()) {
+        Ok(Ok(days)) => {
+            write_state(&shared_state).purge_completed_after =
+                Some(Duration::from_secs(days.saturating_mul(24 * 60 * 60)));
+        }
+        Ok(Err(err)) => tracing::warn!("ignoring TODO_PURGE_COMPLETED_AFTER_DAYS: {}", err),
+        Err(_) => {}
+    }
+
+    let roll_interval = match std::env::var("TODO_ROLL_INTERVAL_SECS").map(|secs| secs.parse()) {
+        Ok(Ok(secs)) if secs > 0 => Duration::from_secs(secs),
+        Ok(Ok(_)) => {
+            tracing::warn!("ignoring TODO_ROLL_INTERVAL_SECS: must be positive");
+            DEFAULT_ROLL_INTERVAL
+        }
+        Ok(Err(err)) => {
+            tracing::warn!("ignoring TODO_ROLL_INTERVAL_SECS: {}", err);
+            DEFAULT_ROLL_INTERVAL
+        }
+        Err(_) => DEFAULT_ROLL_INTERVAL,
+    };
+
+    let (shutdown, shutdown_rx) = watch::channel(false);
+    let roller = tokio::spawn(roll_recurring_periodically(
+        shared_state.clone(),
+        roll_interval,
+        shutdown_rx,
+    ));
+
     let app = app(shared_state);
 
     #[allow(clippy::unwrap_used)]
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            tracing::debug!("shutting down");
+        })
         .await
         .unwrap();
+
+    let _ = shutdown.send(true);
+    let _ = roller.await;
+}
+
+/// Rolls recurring todos and purges old completed ones every `period` until `shutdown` flips
+/// to true.
+async fn roll_recurring_periodically(
+    shared_state: SharedState,
+    period: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let rolled = roll_recurring(&shared_state, SystemTime::now());
+
+                if !rolled.is_empty() {
+                    tracing::debug!("rolled {} recurring todos", rolled.len());
+                }
+
+                let purged = purge_old_completed(&shared_state, SystemTime::now());
+
+                if purged > 0 {
+                    tracing::debug!("purged {} old completed todos", purged);
+                }
+            }
+            _ = shutdown.changed() => break,
+        }
+    }
+}
+
+/// Brings back completed recurring todos that are due at `now`, returning the new todos.
+/// Nothing is rolled in read-only mode.
+///
+/// The write lock is held just for the roll itself.
+pub fn roll_recurring(shared_state: &SharedState, now: SystemTime) -> Vec<Todo> {
+    let mut state = write_state(shared_state);
+
+    if state.read_only.load(Ordering::Relaxed) {
+        return Vec::new();
+    }
+
+    state.roll_recurring(now)
+}
+
+/// Removes todos completed longer than `AppState::purge_completed_after` before `now`,
+/// returning how many were removed; nothing is removed when it is unset or in read-only mode.
+pub fn purge_old_completed(shared_state: &SharedState, now: SystemTime) -> u32 {
+    let mut state = write_state(shared_state);
+    let Some(age) = state.purge_completed_after else {
+        return 0;
+    };
+
+    if state.read_only.load(Ordering::Relaxed) {
+        return 0;
+    }
+
+    let purged = state.todo_repo.purge_completed_older_than(age, now);
+
+    if purged > 0 {
+        Metrics::add(&state.metrics.todos_deleted, purged as usize);
+        state.publish(TodoEvent::DeletedCompleted);
+    }
+
+    purged
+}
+
+/// Whether `request` changes todos, i.e. is a `/todo`, `/t/` or `/admin` request other than
+/// `GET` or `HEAD`. `/admin/readonly` is left out so read-only mode can always be switched off.
+fn is_write<B>(request: &Request<B>) -> bool {
+    let path = request.uri().path();
+
+    (path.starts_with("/todo")
+        || path.starts_with("/t/")
+        || (path.starts_with("/admin") && path != "/admin/readonly"))
+        && !matches!(*request.method(), Method::GET | Method::HEAD)
+}
+
+async fn reject_writes_when_read_only<B>(
+    State(state_lock): State<StateLock>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if is_write(&request) {
+        match state_lock.read().await {
+            Ok(state) if state.read_only.load(Ordering::Relaxed) => {
+                return AppError::ReadOnly.into_response();
+            }
+            Ok(_) => {}
+            Err(err) => return err.into_response(),
+        }
+    }
+
+    next.run(request).await
+}
+
+async fn limit_writes<B>(
+    State(state_lock): State<StateLock>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if is_write(&request) {
+        let client = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let mut state = match state_lock.write().await {
+            Ok(state) => state,
+            Err(err) => return err.into_response(),
+        };
+
+        if let Some(limiter) = &mut state.write_limiter {
+            if !limiter.check(client, Instant::now()) {
+                return StatusCode::TOO_MANY_REQUESTS.into_response();
+            }
+        }
+    }
+
+    next.run(request).await
+}
+
+/// Answers clients whose `Accept` asks for JSON with an `ErrorResponse` on every error,
+/// including rejections from extractors and middleware that only send plain text.
+async fn json_errors<B>(request: Request<B>, next: Next<B>) -> Response {
+    let wants_json = accepts_json(request.headers());
+    let response = next.run(request).await;
+    let status = response.status();
+
+    if !wants_json || !(status.is_client_error() || status.is_server_error()) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let error = match parts.extensions.remove::<ErrorResponse>() {
+        Some(error) => error,
+        None => {
+            let is_text = parts
+                .headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("text/plain"));
+            let mut text = Vec::new();
+            let mut body = body;
+
+            if is_text {
+                while let Some(Ok(chunk)) = body.data().await {
+                    text.extend_from_slice(&chunk);
+                }
+            }
+
+            let text = String::from_utf8_lossy(&text).trim().to_string();
+            let error = if text.is_empty() {
+                status.canonical_reason().unwrap_or("Error").to_string()
+            } else {
+                text
+            };
+
+            ErrorResponse {
+                error,
+                code: error_code(status),
+            }
+        }
+    };
+
+    parts.headers.remove(header::CONTENT_TYPE);
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    let mut response = (status, Json(error)).into_response();
+    response.headers_mut().extend(parts.headers);
+    response
+}
+
+/// `code` of errors that did not come from `AppError`, named after the status, e.g.
+/// `payload_too_large`.
+fn error_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable_entity",
+        StatusCode::TOO_MANY_REQUESTS => "too_many_requests",
+        StatusCode::SERVICE_UNAVAILABLE => "service_unavailable",
+        status if status.is_client_error() => "client_error",
+        _ => "server_error",
+    }
+}
+
+async fn require_admin<B>(
+    State(state_lock): State<StateLock>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let admin_token = match state_lock.read().await {
+        Ok(state) => state.admin_token.clone(),
+        Err(err) => return err.into_response(),
+    };
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match (admin_token, bearer) {
+        (Some(expected), Some(provided)) if expected == provided => next.run(request).await,
+        _ => StatusCode::FORBIDDEN.into_response(),
+    }
+}
+
+async fn get_health() -> &'static str {
+    "ok"
+}
+
+async fn get_ready(state_lock: StateLock) -> (StatusCode, &'static str) {
+    // The store is in memory, so it is reachable unless a panic poisoned the lock or the lock
+    // is held past its timeout.
+    if !state_lock.shared_state.is_poisoned() && state_lock.read().await.is_ok() {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    }
+}
+
+async fn get_metrics(state_lock: StateLock) -> Result<impl IntoResponse, AppError> {
+    let state = state_lock.read().await?;
+    let velocity = state.todo_repo.velocity(VELOCITY_WINDOW, SystemTime::now());
+
+    let mut body = String::new();
+
+    writeln!(
+        body,
+        "# HELP todomvc_completion_velocity Todos completed per hour over the trailing day."
+    )
+    .unwrap();
+    writeln!(body, "# TYPE todomvc_completion_velocity gauge").unwrap();
+    writeln!(body, "todomvc_completion_velocity {}", velocity).unwrap();
+
+    let counters = [
+        ("created", &state.metrics.todos_created),
+        ("completed", &state.metrics.todos_completed),
+        ("deleted", &state.metrics.todos_deleted),
+    ];
+
+    for (name, counter) in counters {
+        writeln!(
+            body,
+            "# HELP todomvc_todos_{}_total Todos {} since startup.",
+            name, name
+        )
+        .unwrap();
+        writeln!(body, "# TYPE todomvc_todos_{}_total counter", name).unwrap();
+        writeln!(
+            body,
+            "todomvc_todos_{}_total {}",
+            name,
+            counter.load(Ordering::Relaxed)
+        )
+        .unwrap();
+    }
+
+    writeln!(
+        body,
+        "# HELP todomvc_todos Todos currently stored, by state."
+    )
+    .unwrap();
+    writeln!(body, "# TYPE todomvc_todos gauge").unwrap();
+    writeln!(
+        body,
+        "todomvc_todos{{state=\"active\"}} {}",
+        state.todo_repo.num_active_items
+    )
+    .unwrap();
+    writeln!(
+        body,
+        "todomvc_todos{{state=\"completed\"}} {}",
+        state.todo_repo.num_completed_items
+    )
+    .unwrap();
+
+    Ok(([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body))
+}
+
+/// Reads the filter this client last selected, defaulting to `AppState::default_filter`.
+fn selected_filter(cookies: &Cookies, state: &AppState) -> TodoListFilter {
+    cookies
+        .signed(&state.cookie_key.0)
+        .get(SELECTED_FILTER_COOKIE)
+        .and_then(|cookie| cookie.value().parse().ok())
+        .unwrap_or(state.default_filter)
+}
+
+fn store_selected_filter(cookies: &Cookies, key: &CookieKey, filter: TodoListFilter) {
+    let cookie = Cookie::build(SELECTED_FILTER_COOKIE, filter.to_string())
+        .path("/")
+        .http_only(true)
+        .finish();
+
+    cookies.signed(&key.0).add(cookie);
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        list_todos,
+        create_todo,
+        toggle_completed_todos,
+        delete_completed_todos,
+        preview_completed_todos,
+        reset_completed_todos,
+        reset_toggle_button,
+        count_todos,
+        grouped_todos,
+        todo_version,
+        export_todos,
+        edit_todo,
+        update_todo,
+        delete_todo
+    ),
+    components(schemas(
+        Todo,
+        TodoListFilter,
+        TodoSort,
+        TodoStatus,
+        TodoSource,
+        Recurrence,
+        TodoToggleAction,
+        CountTodosResponse,
+        GroupedTodosResponse,
+        TodoVersionResponse,
+        CreateTodoForm,
+        UpdateTodoForm
+    ))
+)]
+struct ApiDoc;
+
+async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
 }
 
 #[derive(Template)]
 #[template(path = "responses/index.html")]
-struct GetIndexResponse;
+struct GetIndexResponse {
+    assets: AssetManifest,
+    filter: String,
+    /// The default filter's todos, rendered so the page is populated before htmx runs.
+    list: ListTodosResponse,
+    base_path: String,
+}
+
+async fn get_index(state_lock: StateLock, cookies: Cookies) -> Result<GetIndexResponse, AppError> {
+    let state = state_lock.read().await?;
+    let filter = state.default_filter;
+    store_selected_filter(&cookies, &state.cookie_key, filter);
+
+    let items = state
+        .todo_repo
+        .sorted_items(&filter, state.todo_repo.default_sort);
 
-async fn get_index() -> Result<GetIndexResponse, AppError> {
-    Ok(GetIndexResponse)
+    Ok(GetIndexResponse {
+        assets: state.assets.clone(),
+        filter: filter.to_string(),
+        list: list_todos_response(&state, items),
+        base_path: state.base_path.clone(),
+    })
 }
 
 #[derive(Template)]
@@ -122,35 +1300,524 @@ struct ListTodosResponse {
     num_completed_items: u32,
     num_active_items: u32,
     num_all_items: u32,
+    completion_pct: u8,
     is_disabled_delete: bool,
     is_disabled_toggle: bool,
     action: TodoToggleAction,
     items: Vec<Todo>,
+    /// How many todos matched, more than `items` holds when the list was capped.
+    num_listed: usize,
+    base_path: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct ListTodosQuery {
-    filter: TodoListFilter,
+    filter: Option<TodoListFilter>,
+    /// Case-insensitive part of the text, at most `MAX_TEXT_LEN` characters long.
+    q: Option<String>,
+    /// Lets `q` match despite typos, closest todos first; fuzzy searches are not paged.
+    fuzzy: Option<bool>,
+    /// Keeps only completed or only open todos, on top of `filter`.
+    completed: Option<bool>,
+    /// Keeps only todos with a due date, or only those without one.
+    has_due: Option<bool>,
+    tag: Option<String>,
+    sort: Option<TodoSort>,
+    /// Shorthand for `sort=CompletedLast`.
+    group_completed: Option<bool>,
+    /// Cursor from `x-next-cursor`; paged lists run newest first and ignore `sort`.
+    #[param(value_type = Option<String>)]
+    after: Option<TodoCursor>,
+    /// Page size, capped at the render limit.
+    #[param(value_type = Option<usize>, minimum = 1)]
+    limit: Option<NonZeroUsize>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/todo",
+    params(
+        ListTodosQuery,
+        ("If-None-Match" = Option<String>, Header, description = "ETag of a list already held")
+    ),
+    responses(
+        (status = 200, description = "Rendered todo list", content_type = "text/html"),
+        (status = 304, description = "Nothing changed since the given ETag"),
+        (status = 400, description = "Search longer than the longest todo text")
+    )
+)]
 async fn list_todos(
-    State(shared_state): State<SharedState>,
-    Query(ListTodosQuery { filter }): Query<ListTodosQuery>,
-) -> Result<ListTodosResponse, AppError> {
-    shared_state.write().unwrap().selected_filter = filter;
+    state_lock: StateLock,
+    cookies: Cookies,
+    headers: HeaderMap,
+    Query(ListTodosQuery {
+        filter,
+        q,
+        fuzzy,
+        completed,
+        has_due,
+        tag,
+        sort,
+        group_completed,
+        after,
+        limit,
+    }): Query<ListTodosQuery>,
+) -> Result<Response, AppError> {
+    // No todo text is longer, and matching longer ones fuzzily gets expensive.
+    if q.as_ref().is_some_and(|q| q.chars().count() > MAX_TEXT_LEN) {
+        return Err(AppError::SearchTooLong);
+    }
+
+    let state = state_lock.read().await?;
+    let filter = match filter {
+        Some(filter) => {
+            store_selected_filter(&cookies, &state.cookie_key, filter);
+            filter
+        }
+        None => selected_filter(&cookies, &state),
+    };
+
+    let sort = if group_completed == Some(true) {
+        TodoSort::CompletedLast
+    } else {
+        sort.unwrap_or(state.todo_repo.default_sort)
+    };
+    let is_paged = after.is_some() || limit.is_some();
+    let params = QueryParams {
+        filter,
+        search: q,
+        completed,
+        has_due,
+        tag,
+        sort,
+        after,
+        limit: is_paged.then(|| {
+            limit.map_or(state.render_limit, |limit| {
+                limit.get().min(state.render_limit)
+            })
+        }),
+    };
+    let fuzzy = fuzzy == Some(true);
+    let etag = list_etag(state.todo_repo.version(), &params, fuzzy);
+
+    if let Some(etag) = &etag {
+        if headers
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|if_none_match| etag_matches(if_none_match, etag))
+        {
+            return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag.clone())]).into_response());
+        }
+    }
+
+    let TodoPage {
+        items,
+        next: next_cursor,
+    } = if fuzzy {
+        TodoPage {
+            items: state.todo_repo.search_fuzzy(&params),
+            next: None,
+        }
+    } else {
+        state.todo_repo.query(&params)
+    };
+
+    let mut response = (
+        [(
+            header::HeaderName::from_static(SELECTED_FILTER_HEADER),
+            filter.to_string(),
+        )],
+        list_todos_response(&state, items),
+    )
+        .into_response();
+
+    if let Some(etag) = etag {
+        response
+            .headers_mut()
+            .insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+    }
+
+    if let Some(next_cursor) = next_cursor {
+        response.headers_mut().insert(
+            header::HeaderName::from_static(NEXT_CURSOR_HEADER),
+            HeaderValue::from_str(&next_cursor.to_string()).unwrap(),
+        );
+    }
 
-    let state = shared_state.read().unwrap();
-    let items = state.todo_repo.list(&filter);
+    Ok(response)
+}
+
+/// Tags a list with the repo version and the normalized query that shaped it. The filter may
+/// come from the cookie rather than the URL, so it is part of the tag too. Filters measured
+/// against the clock get no tag, as their lists change without a new version.
+fn list_etag(version: u64, params: &QueryParams, fuzzy: bool) -> Option<String> {
+    if params.filter.depends_on_clock() {
+        return None;
+    }
 
-    Ok(ListTodosResponse {
+    let search = params
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|search| !search.is_empty())
+        .map(str::to_lowercase);
+    let tag = params.tag.as_deref().map(normalize_tag);
+    let mut hasher = DefaultHasher::new();
+
+    (search, tag, params.completed, params.has_due, fuzzy).hash(&mut hasher);
+    (params.sort, params.after, params.limit).hash(&mut hasher);
+
+    Some(format!(
+        "\"{}-{}-{:016x}\"",
+        version,
+        params.filter,
+        hasher.finish()
+    ))
+}
+
+fn list_todos_response(state: &AppState, items: Vec<Todo>) -> ListTodosResponse {
+    let action = TodoToggleAction::for_items(&items);
+    let (items, num_listed) = state.capped(items);
+
+    ListTodosResponse {
         num_completed_items: state.todo_repo.num_completed_items,
         num_active_items: state.todo_repo.num_active_items,
         num_all_items: state.todo_repo.num_all_items,
+        completion_pct: state.todo_repo.completion_pct(),
         is_disabled_delete: state.todo_repo.num_completed_items == 0,
         is_disabled_toggle: state.todo_repo.num_all_items == 0,
-        action: state.toggle_action,
+        action,
         items,
-    })
+        num_listed,
+        base_path: state.base_path.clone(),
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct CountTodosResponse {
+    completed: u32,
+    /// Part of `active` too.
+    in_progress: u32,
+    active: u32,
+    all: u32,
+    /// Share of todos completed, rounded down, so 100 only once every todo is done.
+    completion_pct: u8,
+}
+
+#[utoipa::path(
+    get,
+    path = "/todo/count",
+    responses((status = 200, description = "Todo counters", body = CountTodosResponse))
+)]
+async fn count_todos(state_lock: StateLock) -> Result<Json<CountTodosResponse>, AppError> {
+    Ok(Json(CountTodosResponse::of(
+        &state_lock.read().await?.todo_repo,
+    )))
+}
+
+impl CountTodosResponse {
+    fn of(todo_repo: &TodoRepo) -> Self {
+        Self {
+            completed: todo_repo.num_completed_items,
+            in_progress: todo_repo.num_in_progress_items,
+            active: todo_repo.num_active_items,
+            all: todo_repo.num_all_items,
+            completion_pct: todo_repo.completion_pct(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct GroupedTodosResponse {
+    active: Vec<Todo>,
+    completed: Vec<Todo>,
+    counts: CountTodosResponse,
+}
+
+#[utoipa::path(
+    get,
+    path = "/todo/grouped",
+    responses((
+        status = 200,
+        description = "Active and completed todos, newest first, with the counters",
+        body = GroupedTodosResponse
+    ))
+)]
+async fn grouped_todos(state_lock: StateLock) -> Result<Json<GroupedTodosResponse>, AppError> {
+    let todo_repo = &state_lock.read().await?.todo_repo;
+
+    Ok(Json(GroupedTodosResponse {
+        active: todo_repo.list(&TodoListFilter::Active),
+        completed: todo_repo.list(&TodoListFilter::Completed),
+        counts: CountTodosResponse::of(todo_repo),
+    }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct TodoVersionResponse {
+    version: u64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/todo/version",
+    responses((status = 200, description = "Number of changes so far", body = TodoVersionResponse))
+)]
+async fn todo_version(state_lock: StateLock) -> Result<Json<TodoVersionResponse>, AppError> {
+    let version = state_lock.read().await?.todo_repo.version();
+
+    Ok(Json(TodoVersionResponse { version }))
+}
+
+async fn todo_summary(state_lock: StateLock) -> Result<String, AppError> {
+    Ok(state_lock.read().await?.todo_repo.summary_line())
+}
+
+#[utoipa::path(
+    get,
+    path = "/todo/export",
+    responses((status = 200, description = "Every todo, newest first", body = [Todo]))
+)]
+async fn export_todos(state_lock: StateLock) -> Result<Json<Vec<Todo>>, AppError> {
+    let items = state_lock
+        .read()
+        .await?
+        .todo_repo
+        .list(&TodoListFilter::All);
+
+    Ok(Json(items))
+}
+
+#[derive(Debug, Serialize)]
+struct ImportTodosResponse {
+    imported: usize,
+    skipped: usize,
+}
+
+async fn import_todos(
+    state_lock: StateLock,
+    Json(todos): Json<Vec<Todo>>,
+) -> Result<Json<ImportTodosResponse>, AppError> {
+    let mut state = state_lock.write().await?;
+    let (imported, skipped) = state.todo_repo.import(todos);
+    Metrics::add(&state.metrics.todos_created, imported);
+
+    if imported > 0 {
+        state.publish(TodoEvent::Imported);
+    }
+
+    Ok(Json(ImportTodosResponse { imported, skipped }))
+}
+
+/// Rolls recurring todos on demand, on top of the periodic roll started by `run`.
+async fn roll_recurring_todos(state_lock: StateLock) -> Result<Json<Vec<Todo>>, AppError> {
+    let mut state = state_lock.write().await?;
+
+    Ok(Json(state.roll_recurring(SystemTime::now())))
+}
+
+#[derive(Debug, Serialize)]
+struct TodoMatrixResponse {
+    #[serde(rename = "do")]
+    do_first: Vec<Todo>,
+    schedule: Vec<Todo>,
+    delegate: Vec<Todo>,
+    eliminate: Vec<Todo>,
+}
+
+async fn todo_matrix(state_lock: StateLock) -> Result<Json<TodoMatrixResponse>, AppError> {
+    let [do_first, schedule, delegate, eliminate] =
+        state_lock.read().await?.todo_repo.by_quadrant();
+
+    Ok(Json(TodoMatrixResponse {
+        do_first,
+        schedule,
+        delegate,
+        eliminate,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum TodoOperation {
+    Create {
+        text: String,
+    },
+    Update {
+        id: Uuid,
+        text: Option<String>,
+        is_completed: Option<bool>,
+    },
+    Delete {
+        id: Uuid,
+    },
+    Toggle {
+        action: TodoToggleAction,
+    },
+}
+
+impl TodoOperation {
+    fn apply(
+        self,
+        todo_repo: &mut TodoRepo,
+        counts: &mut TransactionCounts,
+    ) -> Result<(), TodoRepoError> {
+        match self {
+            Self::Create { text } => {
+                todo_repo.create_unique_from(&text, TodoSource::Api)?;
+                counts.created += 1;
+            }
+            Self::Update {
+                id,
+                text,
+                is_completed,
+            } => {
+                let outcome = todo_repo.update(
+                    &id,
+                    TodoUpdate {
+                        text,
+                        is_completed,
+                        ..Default::default()
+                    },
+                )?;
+
+                if outcome.completion_changed && outcome.todo.is_completed() {
+                    counts.completed += 1;
+                }
+            }
+            Self::Delete { id } => {
+                todo_repo.delete(&id)?;
+                counts.deleted += 1;
+            }
+            Self::Toggle { action } => {
+                let num_completed_before = todo_repo.num_completed_items;
+                todo_repo.toggle_completed(&action, &TodoListFilter::All);
+                counts.completed += todo_repo
+                    .num_completed_items
+                    .saturating_sub(num_completed_before)
+                    as usize;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What the operations of a transaction did, added to `Metrics` once it is kept.
+#[derive(Debug, Default)]
+struct TransactionCounts {
+    created: usize,
+    completed: usize,
+    deleted: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ApplyTransactionResponse {
+    applied: usize,
+}
+
+/// Applies every operation on a copy of the repo and keeps it only if all of them succeed.
+async fn apply_transaction(
+    state_lock: StateLock,
+    Json(operations): Json<Vec<TodoOperation>>,
+) -> Result<Json<ApplyTransactionResponse>, AppError> {
+    let mut state = state_lock.write().await?;
+    let mut todo_repo = state.todo_repo.clone();
+    let applied = operations.len();
+    let mut counts = TransactionCounts::default();
+
+    for operation in operations {
+        operation.apply(&mut todo_repo, &mut counts)?;
+    }
+
+    state.todo_repo = todo_repo;
+    Metrics::add(&state.metrics.todos_created, counts.created);
+    Metrics::add(&state.metrics.todos_completed, counts.completed);
+    Metrics::add(&state.metrics.todos_deleted, counts.deleted);
+
+    if applied > 0 {
+        state.publish(TodoEvent::Transaction);
+    }
+
+    Ok(Json(ApplyTransactionResponse { applied }))
+}
+
+#[derive(Debug, Deserialize)]
+struct SetCompletedTodosRequest {
+    ids: Vec<Uuid>,
+    is_completed: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SetCompletedTodosResponse {
+    affected: usize,
+}
+
+/// Checks or unchecks the listed todos; `affected` leaves out those that already matched.
+async fn set_completed_todos(
+    state_lock: StateLock,
+    Json(request): Json<SetCompletedTodosRequest>,
+) -> Result<Json<SetCompletedTodosResponse>, AppError> {
+    let mut state = state_lock.write().await?;
+    let affected = state
+        .todo_repo
+        .set_completed_many(&request.ids, request.is_completed)
+        .len();
+
+    if affected > 0 {
+        if request.is_completed {
+            Metrics::add(&state.metrics.todos_completed, affected);
+        }
+
+        state.publish(TodoEvent::Toggled);
+    }
+
+    Ok(Json(SetCompletedTodosResponse { affected }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RetagTodosRequest {
+    ids: Vec<Uuid>,
+    tag: String,
+}
+
+#[derive(Debug, Serialize)]
+struct RetagTodosResponse {
+    affected: usize,
+}
+
+/// Tags the listed todos; `affected` leaves out those that already carried the tag.
+async fn add_tag_to_todos(
+    state_lock: StateLock,
+    Json(request): Json<RetagTodosRequest>,
+) -> Result<Json<RetagTodosResponse>, AppError> {
+    let mut state = state_lock.write().await?;
+    let changed = state.todo_repo.add_tag_many(&request.ids, &request.tag)?;
+
+    Ok(Json(retagged(&state, changed)))
+}
+
+/// Untags the listed todos; `affected` leaves out those that did not carry the tag.
+async fn remove_tag_from_todos(
+    state_lock: StateLock,
+    Json(request): Json<RetagTodosRequest>,
+) -> Result<Json<RetagTodosResponse>, AppError> {
+    let mut state = state_lock.write().await?;
+    let changed = state
+        .todo_repo
+        .remove_tag_many(&request.ids, &request.tag)?;
+
+    Ok(Json(retagged(&state, changed)))
+}
+
+fn retagged(state: &AppState, changed: Vec<Uuid>) -> RetagTodosResponse {
+    for id in &changed {
+        state.publish(TodoEvent::Updated(*id));
+    }
+
+    RetagTodosResponse {
+        affected: changed.len(),
+    }
 }
 
 #[derive(Template)]
@@ -159,39 +1826,177 @@ struct CreateTodoResponse {
     num_completed_items: u32,
     num_active_items: u32,
     num_all_items: u32,
+    completion_pct: u8,
     is_disabled_toggle: bool,
     action: TodoToggleAction,
     item: Option<Todo>,
+    /// Swaps in an empty new todo input, out of band.
+    reset_input: bool,
+    base_path: String,
+}
+
+/// Creates one todo per non-blank line and renders the selected filter's list.
+async fn create_todos_batch(
+    state_lock: StateLock,
+    cookies: Cookies,
+    BatchLines(lines, source): BatchLines,
+) -> Result<impl IntoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+    let created = state.todo_repo.create_many(&lines, source);
+    Metrics::add(&state.metrics.todos_created, created.len());
+
+    for todo in &created {
+        state.publish(TodoEvent::Created(todo.id));
+    }
+
+    let items = state
+        .todo_repo
+        .sorted_items(&filter, state.todo_repo.default_sort);
+
+    Ok(list_todos_response(&state, items))
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateTodoForm {
     text: String,
+    description: Option<String>,
+    tags: Option<String>,
+    recurrence: Option<Recurrence>,
+    color: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CreateTodoQuery {
+    #[serde(default)]
+    allow_duplicates: bool,
 }
 
+#[utoipa::path(
+    post,
+    path = "/todo",
+    params(
+        CreateTodoQuery,
+        ("Idempotency-Key" = Option<String>, Header, description = "Repeated by retries of the same create, which then return the todo it made")
+    ),
+    request_body(content = CreateTodoForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Rendered new todo", content_type = "text/html"),
+        (status = 409, description = "An active todo with the same text exists")
+    )
+)]
 async fn create_todo(
-    State(shared_state): State<SharedState>,
-    Form(CreateTodoForm { text }): Form<CreateTodoForm>,
-) -> Result<CreateTodoResponse, AppError> {
-    let mut state = shared_state.write().unwrap();
-    let item = state.todo_repo.create(&text);
+    state_lock: StateLock,
+    cookies: Cookies,
+    Query(CreateTodoQuery { allow_duplicates }): Query<CreateTodoQuery>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Form(CreateTodoForm {
+        text,
+        description,
+        tags,
+        recurrence,
+        color,
+    }): Form<CreateTodoForm>,
+) -> Result<Response, AppError> {
+    check_description(description.as_deref())?;
+
+    // Checked upfront so an invalid color does not leave a half-created todo behind.
+    if color
+        .as_deref()
+        .map(parse_color)
+        .is_some_and(|color| color.is_err())
+    {
+        return Err(TodoRepoError::InvalidColor.into());
+    }
+
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+    let client = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let idempotency_key = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|key| key.to_str().ok());
+    let replayed = idempotency_key
+        .and_then(|key| state.idempotency_keys.get(client, key))
+        .and_then(|id| state.todo_repo.get(&id).ok());
+
+    if let Some(item) = replayed {
+        return Ok(created_from_form(create_todo_response(
+            &state, filter, item,
+        )));
+    }
+
+    let mut item = if allow_duplicates {
+        state.todo_repo.create(&text)?
+    } else {
+        state.todo_repo.create_unique(&text)?
+    };
+
+    if description.is_some() || tags.is_some() || recurrence.is_some() || color.is_some() {
+        item = state
+            .todo_repo
+            .update(
+                &item.id,
+                TodoUpdate {
+                    description,
+                    tags: tags.as_deref().map(parse_tags),
+                    recurrence: recurrence.map(Some),
+                    color,
+                    ..Default::default()
+                },
+            )?
+            .todo;
+    }
+
+    state.publish(TodoEvent::Created(item.id));
+    Metrics::add(&state.metrics.todos_created, 1);
+
+    if let Some(key) = idempotency_key {
+        state.idempotency_keys.insert(client, key, item.id);
+    }
+
+    Ok(created_from_form(create_todo_response(
+        &state, filter, item,
+    )))
+}
+
+/// Lets the form clear and refocus its input for the next todo.
+fn created_from_form(mut response: CreateTodoResponse) -> Response {
+    response.reset_input = true;
+
+    (
+        [(
+            header::HeaderName::from_static(HX_TRIGGER_HEADER),
+            CREATED_TRIGGER,
+        )],
+        response,
+    )
+        .into_response()
+}
 
-    let item = if state.selected_filter == TodoListFilter::Completed {
+fn create_todo_response(
+    state: &AppState,
+    filter: TodoListFilter,
+    item: Todo,
+) -> CreateTodoResponse {
+    let item = if filter == TodoListFilter::Completed {
         None
     } else {
         Some(item)
     };
 
-    state.toggle_action = TodoToggleAction::Check;
-
-    Ok(CreateTodoResponse {
+    CreateTodoResponse {
         num_completed_items: state.todo_repo.num_completed_items,
         num_active_items: state.todo_repo.num_active_items,
         num_all_items: state.todo_repo.num_all_items,
+        completion_pct: state.todo_repo.completion_pct(),
         is_disabled_toggle: false,
-        action: state.toggle_action,
+        action: state.todo_repo.toggle_action(&filter),
         item,
-    })
+        reset_input: false,
+        base_path: state.base_path.clone(),
+    }
 }
 
 #[derive(Template)]
@@ -200,40 +2005,120 @@ struct ToggleCompletedTodosResponse {
     num_completed_items: u32,
     num_active_items: u32,
     num_all_items: u32,
+    completion_pct: u8,
     is_disabled_delete: bool,
     is_disabled_toggle: bool,
     action: TodoToggleAction,
     items: Vec<Todo>,
+    /// How many todos matched, more than `items` holds when the list was capped.
+    num_listed: usize,
+    base_path: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct ToggleCompletedTodosQuery {
     action: TodoToggleAction,
 }
 
+#[utoipa::path(
+    patch,
+    path = "/todo",
+    params(ToggleCompletedTodosQuery),
+    responses((status = 200, description = "Rendered todo list", content_type = "text/html"))
+)]
 async fn toggle_completed_todos(
-    State(shared_state): State<SharedState>,
+    state_lock: StateLock,
+    cookies: Cookies,
     Query(ToggleCompletedTodosQuery { action }): Query<ToggleCompletedTodosQuery>,
 ) -> Result<ToggleCompletedTodosResponse, AppError> {
-    let mut state = shared_state.write().unwrap();
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
 
-    state.toggle_action = match action {
-        TodoToggleAction::Uncheck => TodoToggleAction::Check,
-        TodoToggleAction::Check => TodoToggleAction::Uncheck,
-    };
+    let num_completed_before = state.todo_repo.num_completed_items;
+    state.todo_repo.toggle_completed(&action, &filter);
+
+    let num_checked = state
+        .todo_repo
+        .num_completed_items
+        .saturating_sub(num_completed_before);
+    Metrics::add(&state.metrics.todos_completed, num_checked as usize);
+    state.publish(TodoEvent::Toggled);
+
+    Ok(toggled_todos_response(&state, filter))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/todo/completed/reset",
+    responses((status = 200, description = "Rendered todo list", content_type = "text/html"))
+)]
+async fn reset_completed_todos(
+    state_lock: StateLock,
+    cookies: Cookies,
+) -> Result<ToggleCompletedTodosResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+
+    if state.todo_repo.uncomplete_all() > 0 {
+        state.publish(TodoEvent::Toggled);
+    }
+
+    Ok(toggled_todos_response(&state, filter))
+}
+
+#[derive(Template)]
+#[template(path = "responses/toggle_completed_button.html")]
+struct ToggleCompletedButtonResponse {
+    is_disabled_toggle: bool,
+    action: TodoToggleAction,
+    base_path: String,
+}
+
+/// Renders the toggle-all button again from the todos the selected filter shows, for when
+/// the page's copy went stale, e.g. after changes made elsewhere.
+#[utoipa::path(
+    post,
+    path = "/todo/toggle/reset",
+    responses((status = 200, description = "Rendered toggle-all button", content_type = "text/html"))
+)]
+async fn reset_toggle_button(
+    state_lock: StateLock,
+    cookies: Cookies,
+) -> Result<ToggleCompletedButtonResponse, AppError> {
+    let state = state_lock.read().await?;
+    let filter = selected_filter(&cookies, &state);
+
+    Ok(ToggleCompletedButtonResponse {
+        is_disabled_toggle: state.todo_repo.num_all_items == 0,
+        action: state.todo_repo.toggle_action(&filter),
+        base_path: state.base_path.clone(),
+    })
+}
 
-    state.todo_repo.toggle_completed(&action);
-    let items = state.todo_repo.list(&state.selected_filter);
+/// The todos `filter` shows after a bulk completion change, with the counters.
+fn toggled_todos_response(
+    state: &AppState,
+    filter: TodoListFilter,
+) -> ToggleCompletedTodosResponse {
+    let items = state
+        .todo_repo
+        .sorted_items(&filter, state.todo_repo.default_sort);
+    let action = TodoToggleAction::for_items(&items);
+    let (items, num_listed) = state.capped(items);
 
-    Ok(ToggleCompletedTodosResponse {
+    ToggleCompletedTodosResponse {
         num_completed_items: state.todo_repo.num_completed_items,
         num_active_items: state.todo_repo.num_active_items,
         num_all_items: state.todo_repo.num_all_items,
+        completion_pct: state.todo_repo.completion_pct(),
         is_disabled_delete: state.todo_repo.num_completed_items == 0,
         is_disabled_toggle: state.todo_repo.num_all_items == 0,
-        action: state.toggle_action,
+        action,
         items,
-    })
+        num_listed,
+        base_path: state.base_path.clone(),
+    }
 }
 
 #[derive(Template)]
@@ -242,30 +2127,109 @@ struct DeleteCompletedTodosResponse {
     num_completed_items: u32,
     num_active_items: u32,
     num_all_items: u32,
+    completion_pct: u8,
     is_disabled_delete: bool,
     is_disabled_toggle: bool,
     action: TodoToggleAction,
     items: Vec<Todo>,
+    /// How many todos matched, more than `items` holds when the list was capped.
+    num_listed: usize,
+    base_path: String,
+}
+
+#[derive(Template)]
+#[template(path = "responses/preview_completed_todos.html")]
+struct PreviewCompletedTodosResponse {
+    items: Vec<Todo>,
+}
+
+/// Lists what `DELETE /todo` would remove, without removing anything.
+#[utoipa::path(
+    get,
+    path = "/todo/completed/preview",
+    responses((status = 200, description = "Rendered completed todos", content_type = "text/html"))
+)]
+async fn preview_completed_todos(
+    state_lock: StateLock,
+) -> Result<PreviewCompletedTodosResponse, AppError> {
+    Ok(PreviewCompletedTodosResponse {
+        items: state_lock
+            .read()
+            .await?
+            .todo_repo
+            .list(&TodoListFilter::Completed),
+    })
 }
 
+#[utoipa::path(
+    delete,
+    path = "/todo",
+    responses((status = 200, description = "Rendered todo list", content_type = "text/html"))
+)]
 async fn delete_completed_todos(
-    State(shared_state): State<SharedState>,
+    state_lock: StateLock,
+    cookies: Cookies,
 ) -> Result<DeleteCompletedTodosResponse, AppError> {
-    let mut state = shared_state.write().unwrap();
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
 
-    state.toggle_action = TodoToggleAction::Check;
+    let num_completed = state.todo_repo.num_completed_items;
     state.todo_repo.delete_completed();
+    Metrics::add(&state.metrics.todos_deleted, num_completed as usize);
+    state.publish(TodoEvent::DeletedCompleted);
 
-    let items = state.todo_repo.list(&state.selected_filter);
+    let items = state
+        .todo_repo
+        .sorted_items(&filter, state.todo_repo.default_sort);
+    let action = TodoToggleAction::for_items(&items);
+    let (items, num_listed) = state.capped(items);
 
     Ok(DeleteCompletedTodosResponse {
         num_completed_items: state.todo_repo.num_completed_items,
         num_active_items: state.todo_repo.num_active_items,
         num_all_items: state.todo_repo.num_all_items,
+        completion_pct: state.todo_repo.completion_pct(),
         is_disabled_delete: true,
         is_disabled_toggle: state.todo_repo.num_all_items == 0,
-        action: state.toggle_action,
+        action,
         items,
+        num_listed,
+        base_path: state.base_path.clone(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ClearTodosQuery {
+    #[serde(default)]
+    confirm: bool,
+}
+
+async fn clear_todos(
+    state_lock: StateLock,
+    Query(ClearTodosQuery { confirm }): Query<ClearTodosQuery>,
+) -> Result<DeleteCompletedTodosResponse, AppError> {
+    if !confirm {
+        return Err(AppError::MissingConfirmation);
+    }
+
+    let mut state = state_lock.write().await?;
+
+    let num_all = state.todo_repo.num_all_items;
+    state.todo_repo.clear();
+    Metrics::add(&state.metrics.todos_deleted, num_all as usize);
+    state.publish(TodoEvent::Cleared);
+
+    Ok(DeleteCompletedTodosResponse {
+        num_completed_items: 0,
+        num_active_items: 0,
+        num_all_items: 0,
+        completion_pct: 0,
+        is_disabled_delete: true,
+        is_disabled_toggle: true,
+        action: TodoToggleAction::Check,
+        items: Vec::new(),
+        num_listed: 0,
+        base_path: state.base_path.clone(),
     })
 }
 
@@ -273,14 +2237,156 @@ async fn delete_completed_todos(
 #[template(path = "responses/edit_todo.html")]
 struct EditTodoResponse {
     item: Todo,
+    base_path: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/todo/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("Accept" = Option<String>, Header, description = "`application/json` for the raw todo"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of the same representation already held"),
+        ("If-Modified-Since" = Option<String>, Header, description = "`Last-Modified` of a todo already held")
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Rendered edit form, or the todo when JSON is accepted",
+            content(("text/html" = String), ("application/json" = Todo))
+        ),
+        (status = 304, description = "Todo unchanged since the given ETag or time"),
+        (status = 404, description = "Todo not found")
+    )
+)]
 async fn edit_todo(
-    State(shared_state): State<SharedState>,
-    Path(id): Path<Uuid>,
-) -> Result<EditTodoResponse, AppError> {
-    let item = shared_state.read().unwrap().todo_repo.get(&id)?;
-    Ok(EditTodoResponse { item })
+    state_lock: StateLock,
+    headers: HeaderMap,
+    TodoId(id): TodoId,
+) -> Result<Response, AppError> {
+    let state = state_lock.read().await?;
+    let item = state.todo_repo.get(&id)?;
+    let is_json = accepts_json(&headers);
+
+    let cache_headers = [
+        (header::ETAG, representation_etag(&item, is_json)),
+        (
+            header::LAST_MODIFIED,
+            httpdate::fmt_http_date(item.last_modified()),
+        ),
+        (header::VARY, header::ACCEPT.to_string()),
+    ];
+
+    // If-Modified-Since only counts without If-None-Match, as the ETag is the finer check.
+    let is_not_modified = match headers.get(header::IF_NONE_MATCH) {
+        Some(if_none_match) => etag_matches(if_none_match, &cache_headers[0].1),
+        None => headers
+            .get(header::IF_MODIFIED_SINCE)
+            .is_some_and(|if_modified_since| !is_modified_since(&item, if_modified_since)),
+    };
+
+    if is_not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, cache_headers).into_response());
+    }
+
+    if is_json {
+        return Ok((cache_headers, Json(item)).into_response());
+    }
+
+    let base_path = state.base_path.clone();
+
+    Ok((cache_headers, EditTodoResponse { item, base_path }).into_response())
+}
+
+/// The `ETag` of the JSON or the HTML form of `todo`, which differ since they are different
+/// bodies; `If-Match` accepts either.
+fn representation_etag(todo: &Todo, is_json: bool) -> String {
+    if is_json {
+        format!("\"{}-json\"", todo.revision)
+    } else {
+        todo.etag()
+    }
+}
+
+/// Whether `todo` changed after `if_modified_since`, which has whole-second precision.
+///
+/// Unparsable dates count as modified so the todo is sent in full.
+fn is_modified_since(todo: &Todo, if_modified_since: &HeaderValue) -> bool {
+    let Some(since) = if_modified_since
+        .to_str()
+        .ok()
+        .and_then(|since| httpdate::parse_http_date(since).ok())
+    else {
+        return true;
+    };
+
+    todo.last_modified()
+        .duration_since(since)
+        .is_ok_and(|newer_by| newer_by >= Duration::from_secs(1))
+}
+
+/// Whether the `Accept` header asks for JSON rather than the default HTML fragments.
+fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("application/json"))
+}
+
+#[derive(Template)]
+#[template(path = "responses/view_todo.html")]
+struct ViewTodoResponse {
+    item: Todo,
+    base_path: String,
+}
+
+async fn view_todo(
+    state_lock: StateLock,
+    TodoId(id): TodoId,
+) -> Result<ViewTodoResponse, AppError> {
+    let state = state_lock.read().await?;
+    let item = state.todo_repo.get(&id)?;
+
+    Ok(ViewTodoResponse {
+        item,
+        base_path: state.base_path.clone(),
+    })
+}
+
+fn check_description(description: Option<&str>) -> Result<(), AppError> {
+    match description {
+        Some(description) if !is_valid_description(description) => {
+            Err(AppError::InvalidDescription)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether a comma-separated list of entity tags, as sent in `If-Match` or `If-None-Match`,
+/// contains `etag` or `*`.
+fn etag_matches(value: &HeaderValue, etag: &str) -> bool {
+    value.to_str().is_ok_and(|value| {
+        value
+            .split(',')
+            .map(str::trim)
+            .any(|candidate| candidate == "*" || candidate == etag)
+    })
+}
+
+/// Fails unless an `If-Match` header, when present, lists one of the todo's current `ETag`s
+/// or `*`.
+fn check_if_match(headers: &HeaderMap, todo: &Todo) -> Result<(), AppError> {
+    let Some(if_match) = headers.get(header::IF_MATCH) else {
+        return Ok(());
+    };
+
+    if etag_matches(if_match, &representation_etag(todo, false))
+        || etag_matches(if_match, &representation_etag(todo, true))
+    {
+        Ok(())
+    } else {
+        Err(AppError::PreconditionFailed)
+    }
 }
 
 #[derive(Template)]
@@ -289,50 +2395,310 @@ struct UpdateTodoResponse {
     num_completed_items: u32,
     num_active_items: u32,
     num_all_items: u32,
+    completion_pct: u8,
     is_disabled_delete: bool,
     is_disabled_toggle: bool,
     action: TodoToggleAction,
     item: Option<Todo>,
+    /// Set when a blank text was ignored, so the unchanged todo is flagged.
+    is_rejected: bool,
+    base_path: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
 struct UpdateTodoForm {
     is_completed: Option<bool>,
+    /// Takes precedence over `is_completed`.
+    status: Option<TodoStatus>,
+    is_urgent: Option<bool>,
+    is_important: Option<bool>,
     text: Option<String>,
+    description: Option<String>,
+    tags: Option<String>,
+    /// Left blank, clears the recurrence.
+    #[serde(default, deserialize_with = "models::clearable::deserialize")]
+    #[schema(value_type = Option<Recurrence>)]
+    recurrence: Option<Option<Recurrence>>,
+    /// An RFC 3339 timestamp, e.g. `2024-05-01T17:00:00Z`; left blank, clears the due date.
+    #[serde(default, deserialize_with = "models::rfc3339::clearable::deserialize")]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    due_at: Option<Option<SystemTime>>,
+    color: Option<String>,
+    /// Revision the todo must still have, answered with `409 Conflict` otherwise.
+    revision: Option<u64>,
 }
 
+#[utoipa::path(
+    patch,
+    path = "/todo/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the todo must still have")
+    ),
+    request_body(content = UpdateTodoForm, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Rendered updated todo", content_type = "text/html"),
+        (status = 404, description = "Todo not found"),
+        (status = 409, description = "Todo no longer has the given revision"),
+        (status = 412, description = "Todo has changed since it was fetched")
+    )
+)]
 async fn update_todo(
-    State(shared_state): State<SharedState>,
-    Path(id): Path<Uuid>,
+    state_lock: StateLock,
+    cookies: Cookies,
+    headers: HeaderMap,
+    TodoId(id): TodoId,
     Form(todo_update): Form<UpdateTodoForm>,
-) -> Result<UpdateTodoResponse, AppError> {
-    let mut state = shared_state.write().unwrap();
-    let item = state
-        .todo_repo
-        .update(&id, todo_update.text, todo_update.is_completed)?;
+) -> Result<Response, AppError> {
+    check_description(todo_update.description.as_deref())?;
 
-    state.toggle_action = if state.todo_repo.num_completed_items == state.todo_repo.num_all_items {
-        TodoToggleAction::Uncheck
-    } else {
-        TodoToggleAction::Check
-    };
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+
+    check_if_match(&headers, &state.todo_repo.get(&id)?)?;
+
+    let outcome = match state.todo_repo.update(
+        &id,
+        TodoUpdate {
+            text: todo_update.text,
+            is_completed: todo_update.is_completed,
+            status: todo_update.status,
+            is_urgent: todo_update.is_urgent,
+            is_important: todo_update.is_important,
+            description: todo_update.description,
+            tags: todo_update.tags.as_deref().map(parse_tags),
+            recurrence: todo_update.recurrence,
+            due_at: todo_update.due_at,
+            color: todo_update.color,
+            expected_revision: todo_update.revision,
+        },
+    ) {
+        // A blanked edit form puts the stored todo back rather than swapping in an error.
+        Err(TodoRepoError::BlankText) => {
+            let outcome = UpdateOutcome {
+                todo: state.todo_repo.get(&id)?,
+                completion_changed: false,
+            };
+            let etag = outcome.todo.etag();
+            let mut response = updated_todo_response(&state, filter, outcome);
 
-    let item = match state.selected_filter {
-        TodoListFilter::Active if item.is_completed => None,
-        TodoListFilter::Active | TodoListFilter::All => Some(item),
-        TodoListFilter::Completed if item.is_completed => Some(item),
-        TodoListFilter::Completed => None,
+            response.is_rejected = true;
+            return Ok(([(header::ETAG, etag)], response).into_response());
+        }
+        outcome => outcome?,
     };
 
-    Ok(UpdateTodoResponse {
+    state.publish(TodoEvent::Updated(id));
+
+    let etag = outcome.todo.etag();
+    let completed = outcome.completion_changed && outcome.todo.is_completed();
+    let response = updated_todo_response(&state, filter, outcome);
+    let mut triggers = Vec::new();
+
+    if response.item.is_none() {
+        triggers.push(LEFT_FILTER_TRIGGER);
+    }
+
+    if completed && state.completion_trigger {
+        triggers.push(COMPLETED_TRIGGER);
+    }
+
+    let mut response = ([(header::ETAG, etag)], response).into_response();
+
+    if !triggers.is_empty() {
+        response.headers_mut().insert(
+            header::HeaderName::from_static(HX_TRIGGER_HEADER),
+            HeaderValue::from_str(&triggers.join(", ")).unwrap(),
+        );
+    }
+
+    Ok(response)
+}
+
+/// Counts a fresh completion.
+fn record_completion_change(state: &AppState, outcome: &UpdateOutcome) {
+    if outcome.completion_changed && outcome.todo.is_completed() {
+        Metrics::add(&state.metrics.todos_completed, 1);
+    }
+}
+
+fn updated_todo_response(
+    state: &AppState,
+    filter: TodoListFilter,
+    outcome: UpdateOutcome,
+) -> UpdateTodoResponse {
+    record_completion_change(state, &outcome);
+
+    let item = Some(outcome.todo).filter(|item| filter.matches(item));
+    let action = state.todo_repo.toggle_action(&filter);
+
+    UpdateTodoResponse {
         num_completed_items: state.todo_repo.num_completed_items,
         num_active_items: state.todo_repo.num_active_items,
         num_all_items: state.todo_repo.num_all_items,
+        completion_pct: state.todo_repo.completion_pct(),
         is_disabled_delete: state.todo_repo.num_completed_items == 0,
         is_disabled_toggle: state.todo_repo.num_all_items == 0,
-        action: state.toggle_action,
+        action,
         item,
-    })
+        is_rejected: false,
+        base_path: state.base_path.clone(),
+    }
+}
+
+async fn complete_todo(
+    state_lock: StateLock,
+    cookies: Cookies,
+    TodoId(id): TodoId,
+) -> Result<UpdateTodoResponse, AppError> {
+    set_todo_completed(&state_lock, &cookies, &id, true).await
+}
+
+async fn incomplete_todo(
+    state_lock: StateLock,
+    cookies: Cookies,
+    TodoId(id): TodoId,
+) -> Result<UpdateTodoResponse, AppError> {
+    set_todo_completed(&state_lock, &cookies, &id, false).await
+}
+
+async fn set_todo_completed(
+    state_lock: &StateLock,
+    cookies: &Cookies,
+    id: &Uuid,
+    is_completed: bool,
+) -> Result<UpdateTodoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(cookies, &state);
+    let outcome = state.todo_repo.set_completed(id, is_completed)?;
+
+    state.publish(TodoEvent::Updated(*id));
+
+    Ok(updated_todo_response(&state, filter, outcome))
+}
+
+#[derive(Debug, Deserialize)]
+struct MoveTodoToFilterQuery {
+    filter: TodoListFilter,
+}
+
+async fn move_todo_to_filter(
+    state_lock: StateLock,
+    cookies: Cookies,
+    TodoId(id): TodoId,
+    Query(MoveTodoToFilterQuery { filter }): Query<MoveTodoToFilterQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let (is_completed, status) = match filter {
+        TodoListFilter::Completed => (Some(true), None),
+        TodoListFilter::Active => (Some(false), None),
+        TodoListFilter::InProgress => (None, Some(TodoStatus::InProgress)),
+        TodoListFilter::All
+        | TodoListFilter::Trash
+        | TodoListFilter::Today
+        | TodoListFilter::ThisWeek
+        | TodoListFilter::Overdue => return Err(AppError::InvalidMoveTarget),
+    };
+
+    let todo_update = UpdateTodoForm {
+        is_completed,
+        status,
+        ..Default::default()
+    };
+
+    update_todo(
+        state_lock,
+        cookies,
+        HeaderMap::new(),
+        TodoId(id),
+        Form(todo_update),
+    )
+    .await
+}
+
+#[derive(Debug, Deserialize)]
+struct ReorderTodoForm {
+    position: u32,
+}
+
+async fn reorder_todo(
+    state_lock: StateLock,
+    TodoId(id): TodoId,
+    Form(ReorderTodoForm { position }): Form<ReorderTodoForm>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    state.todo_repo.reorder(&id, position)?;
+
+    state.publish(TodoEvent::Updated(id));
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+struct AppendTextForm {
+    text: String,
+}
+
+async fn append_todo_text(
+    state_lock: StateLock,
+    cookies: Cookies,
+    TodoId(id): TodoId,
+    Form(AppendTextForm { text }): Form<AppendTextForm>,
+) -> Result<UpdateTodoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+    let outcome = state.todo_repo.append_text(&id, &text)?;
+
+    state.publish(TodoEvent::Updated(id));
+
+    Ok(updated_todo_response(&state, filter, outcome))
+}
+
+#[derive(Debug, Deserialize)]
+struct AddSubtaskForm {
+    text: String,
+}
+
+async fn add_subtask(
+    state_lock: StateLock,
+    cookies: Cookies,
+    TodoId(id): TodoId,
+    Form(AddSubtaskForm { text }): Form<AddSubtaskForm>,
+) -> Result<UpdateTodoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+    let outcome = state.todo_repo.add_subtask(&id, &text)?;
+
+    state.publish(TodoEvent::Updated(id));
+
+    Ok(updated_todo_response(&state, filter, outcome))
+}
+
+async fn toggle_subtask(
+    state_lock: StateLock,
+    cookies: Cookies,
+    SubtaskId(id, subtask_id): SubtaskId,
+) -> Result<UpdateTodoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+    let outcome = state.todo_repo.toggle_subtask(&id, &subtask_id)?;
+
+    state.publish(TodoEvent::Updated(id));
+
+    Ok(updated_todo_response(&state, filter, outcome))
+}
+
+async fn remove_subtask(
+    state_lock: StateLock,
+    cookies: Cookies,
+    SubtaskId(id, subtask_id): SubtaskId,
+) -> Result<UpdateTodoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+    let outcome = state.todo_repo.remove_subtask(&id, &subtask_id)?;
+
+    state.publish(TodoEvent::Updated(id));
+
+    Ok(updated_todo_response(&state, filter, outcome))
 }
 
 #[derive(Template)]
@@ -341,30 +2707,178 @@ struct DeleteTodoResponse {
     num_completed_items: u32,
     num_active_items: u32,
     num_all_items: u32,
+    completion_pct: u8,
     is_disabled_delete: bool,
     is_disabled_toggle: bool,
     action: TodoToggleAction,
+    base_path: String,
 }
 
+#[utoipa::path(
+    delete,
+    path = "/todo/{id}",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("If-Match" = Option<String>, Header, description = "ETag the todo must still have")
+    ),
+    responses(
+        (status = 200, description = "Rendered counters", content_type = "text/html"),
+        (status = 404, description = "Todo not found"),
+        (status = 412, description = "Todo has changed since it was fetched")
+    )
+)]
 async fn delete_todo(
-    State(shared_state): State<SharedState>,
-    Path(id): Path<Uuid>,
+    state_lock: StateLock,
+    cookies: Cookies,
+    headers: HeaderMap,
+    TodoId(id): TodoId,
 ) -> Result<DeleteTodoResponse, AppError> {
-    let mut state = shared_state.write().unwrap();
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+
+    check_if_match(&headers, &state.todo_repo.get(&id)?)?;
+
     state.todo_repo.delete(&id)?;
+    state.publish(TodoEvent::Deleted(id));
+    Metrics::add(&state.metrics.todos_deleted, 1);
 
-    state.toggle_action = if state.todo_repo.num_all_items == 0 {
-        TodoToggleAction::Check
-    } else {
-        TodoToggleAction::Uncheck
-    };
+    Ok(DeleteTodoResponse {
+        num_completed_items: state.todo_repo.num_completed_items,
+        num_active_items: state.todo_repo.num_active_items,
+        num_all_items: state.todo_repo.num_all_items,
+        completion_pct: state.todo_repo.completion_pct(),
+        is_disabled_delete: state.todo_repo.num_completed_items == 0,
+        is_disabled_toggle: state.todo_repo.num_all_items == 0,
+        action: state.todo_repo.toggle_action(&filter),
+        base_path: state.base_path.clone(),
+    })
+}
 
+async fn restore_todo(
+    state_lock: StateLock,
+    cookies: Cookies,
+    TodoId(id): TodoId,
+) -> Result<DeleteTodoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+
+    state.todo_repo.restore(&id)?;
+
+    state.publish(TodoEvent::Restored(id));
+
+    // Restoring takes the todo out of the trash view, just like deleting does elsewhere.
     Ok(DeleteTodoResponse {
         num_completed_items: state.todo_repo.num_completed_items,
         num_active_items: state.todo_repo.num_active_items,
         num_all_items: state.todo_repo.num_all_items,
+        completion_pct: state.todo_repo.completion_pct(),
         is_disabled_delete: state.todo_repo.num_completed_items == 0,
         is_disabled_toggle: state.todo_repo.num_all_items == 0,
-        action: state.toggle_action,
+        action: state.todo_repo.toggle_action(&filter),
+        base_path: state.base_path.clone(),
     })
 }
+
+async fn duplicate_todo(
+    state_lock: StateLock,
+    cookies: Cookies,
+    TodoId(id): TodoId,
+) -> Result<CreateTodoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+    let item = state.todo_repo.duplicate(&id)?;
+
+    state.publish(TodoEvent::Created(item.id));
+    Metrics::add(&state.metrics.todos_created, 1);
+
+    Ok(create_todo_response(&state, filter, item))
+}
+
+/// Empties the trash and renders the selected filter's list.
+async fn purge_todos(
+    state_lock: StateLock,
+    cookies: Cookies,
+) -> Result<impl IntoResponse, AppError> {
+    let mut state = state_lock.write().await?;
+    let filter = selected_filter(&cookies, &state);
+
+    if state.todo_repo.purge() > 0 {
+        state.publish(TodoEvent::Purged);
+    }
+
+    let items = state
+        .todo_repo
+        .sorted_items(&filter, state.todo_repo.default_sort);
+
+    Ok(list_todos_response(&state, items))
+}
+
+async fn stream_todo_events(
+    state_lock: StateLock,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let receiver = state_lock.read().await?.events.subscribe();
+
+    // Lagging subscribers simply miss the events they could not keep up with.
+    let stream = BroadcastStream::new(receiver).filter_map(|event| {
+        event.ok().map(|event| {
+            let data = event
+                .id()
+                .map_or_else(|| String::from("all"), |id| id.to_string());
+
+            Ok(Event::default().event(event.to_string()).data(data))
+        })
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Debug, Deserialize)]
+struct DeduplicateTodosQuery {
+    keep: Option<TodoDedupKeep>,
+}
+
+#[derive(Debug, Serialize)]
+struct DeduplicateTodosResponse {
+    removed: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct SetReadOnlyQuery {
+    enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct SetReadOnlyResponse {
+    read_only: bool,
+}
+
+async fn set_read_only(
+    state_lock: StateLock,
+    Query(SetReadOnlyQuery { enabled }): Query<SetReadOnlyQuery>,
+) -> Result<Json<SetReadOnlyResponse>, AppError> {
+    state_lock
+        .read()
+        .await?
+        .read_only
+        .store(enabled, Ordering::Relaxed);
+    tracing::debug!("read-only mode set to {}", enabled);
+
+    Ok(Json(SetReadOnlyResponse { read_only: enabled }))
+}
+
+async fn deduplicate_todos(
+    state_lock: StateLock,
+    Query(DeduplicateTodosQuery { keep }): Query<DeduplicateTodosQuery>,
+) -> Result<Json<DeduplicateTodosResponse>, AppError> {
+    let mut state = state_lock.write().await?;
+    let removed = state
+        .todo_repo
+        .deduplicate(keep.unwrap_or(TodoDedupKeep::Oldest));
+    Metrics::add(&state.metrics.todos_deleted, removed);
+
+    if removed > 0 {
+        state.publish(TodoEvent::Deduplicated);
+    }
+
+    Ok(Json(DeduplicateTodosResponse { removed }))
+}
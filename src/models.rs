@@ -1,31 +1,456 @@
+use crate::util::{encode_base62, format_duration, relative_time};
 use serde::{Deserialize, Serialize};
-use std::{fmt, time::SystemTime};
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Serialized with an `is_completed` mirror of `status` next to its fields, for clients and
+/// exports from before `status`; see the `Serialize` and `Deserialize` impls below.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, ToSchema)]
+#[serde(remote = "Self")]
 pub struct Todo {
-    pub is_completed: bool,
+    #[serde(default)]
+    pub status: TodoStatus,
+    #[serde(default)]
+    pub is_urgent: bool,
+    #[serde(default)]
+    pub is_important: bool,
+    #[serde(with = "rfc3339")]
+    #[schema(value_type = String, format = DateTime)]
     pub created_at: SystemTime,
+    #[serde(default, with = "rfc3339::option")]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub completed_at: Option<SystemTime>,
+    /// Set while the todo sits in the trash, see `TodoListFilter::Trash`.
+    #[serde(default, with = "rfc3339::option")]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub deleted_at: Option<SystemTime>,
+    /// When the todo last changed after being created, see `Todo::touch`.
+    #[serde(default, with = "rfc3339::option")]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub updated_at: Option<SystemTime>,
+    /// When the todo should be done by, see `TodoListFilter::Overdue`.
+    #[serde(default, with = "rfc3339::option")]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub due_at: Option<SystemTime>,
+    /// How the todo was created; only part of the JSON, never rendered.
+    #[serde(default)]
+    pub source: TodoSource,
+    /// Insertion order assigned by `TodoRepo`, breaks ties on `created_at`.
+    #[serde(default)]
+    pub seq: u64,
+    /// Slot in the manual order, kept dense by `TodoRepo::reorder`.
+    #[serde(default)]
+    pub position: u32,
+    /// Bumped by `TodoRepo` on every change, backs the todo's `ETag`.
+    #[serde(default)]
+    pub revision: u64,
     pub text: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// One of `COLORS`.
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub subtasks: Vec<Subtask>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
     pub id: Uuid,
 }
 
+impl Serialize for Todo {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct WithLegacy<'a> {
+            #[serde(flatten, serialize_with = "serialize_todo")]
+            todo: &'a Todo,
+            is_completed: bool,
+        }
+
+        fn serialize_todo<S: serde::Serializer>(
+            todo: &&Todo,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            Todo::serialize(todo, serializer)
+        }
+
+        WithLegacy {
+            todo: self,
+            is_completed: self.is_completed(),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Without a `status`, a legacy `"is_completed": true` reads as `Done`.
+impl<'de> Deserialize<'de> for Todo {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct WithLegacy {
+            #[serde(flatten, with = "Todo")]
+            todo: Todo,
+            status: Option<TodoStatus>,
+            is_completed: Option<bool>,
+        }
+
+        let WithLegacy {
+            mut todo,
+            status,
+            is_completed,
+        } = WithLegacy::deserialize(deserializer)?;
+
+        todo.status = match (status, is_completed) {
+            (Some(status), _) => status,
+            (None, Some(true)) => TodoStatus::Done,
+            (None, _) => TodoStatus::Todo,
+        };
+
+        Ok(todo)
+    }
+}
+
 impl Todo {
     pub fn new(text: &str) -> Self {
         Self {
-            is_completed: false,
+            status: TodoStatus::Todo,
+            is_urgent: false,
+            is_important: false,
             created_at: SystemTime::now(),
+            completed_at: None,
+            deleted_at: None,
+            updated_at: None,
+            due_at: None,
+            source: TodoSource::Web,
+            seq: 0,
+            position: 0,
+            revision: 0,
             text: String::from(text),
+            description: None,
+            tags: Vec::new(),
+            color: None,
+            subtasks: Vec::new(),
+            recurrence: None,
             id: Uuid::new_v4(),
         }
     }
+
+    /// Whether the todo is `TodoStatus::Done`.
+    pub fn is_completed(&self) -> bool {
+        self.status == TodoStatus::Done
+    }
+
+    /// Moves the todo to `Done`, or back to `Todo` when unchecked.
+    pub fn set_completed(&mut self, is_completed: bool) {
+        self.set_status(if is_completed {
+            TodoStatus::Done
+        } else {
+            TodoStatus::Todo
+        });
+    }
+
+    /// Stamps `completed_at` on reaching `Done` and clears it on leaving.
+    pub fn set_status(&mut self, status: TodoStatus) {
+        self.status = status;
+        self.completed_at = (status == TodoStatus::Done).then(SystemTime::now);
+    }
+
+    /// Bulma modifier matching the todo's color label, if any.
+    pub fn color_class(&self) -> Option<&'static str> {
+        match self.color.as_deref()? {
+            "red" => Some("is-danger"),
+            "green" => Some("is-success"),
+            "blue" => Some("is-link"),
+            "yellow" => Some("is-warning"),
+            _ => None,
+        }
+    }
+
+    /// Base62 form of `seq`, unique within a repo and served under `/t/:short_id`.
+    pub fn short_id(&self) -> String {
+        encode_base62(self.seq)
+    }
+
+    /// Records a change: bumps the revision and stamps `updated_at`.
+    pub fn touch(&mut self) {
+        self.revision += 1;
+        self.updated_at = Some(SystemTime::now());
+    }
+
+    /// Whether the todo is still open past its due date.
+    pub fn is_overdue(&self) -> bool {
+        self.is_overdue_at(SystemTime::now())
+    }
+
+    /// Like `is_overdue`, measured at `now`.
+    pub fn is_overdue_at(&self, now: SystemTime) -> bool {
+        !self.is_completed() && self.due_at.is_some_and(|due_at| due_at < now)
+    }
+
+    /// When the todo last changed, its creation counting as the first change.
+    pub fn last_modified(&self) -> SystemTime {
+        self.updated_at.unwrap_or(self.created_at)
+    }
+
+    /// Strong entity tag derived from the revision, quoted as sent in headers.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.revision)
+    }
+
+    /// How long a completed todo took, clamped to zero if the clock went backwards.
+    pub fn completion_duration(&self) -> Option<Duration> {
+        let completed_at = self.completed_at.filter(|_| self.is_completed())?;
+        Some(
+            completed_at
+                .duration_since(self.created_at)
+                .unwrap_or(Duration::ZERO),
+        )
+    }
+
+    pub fn completion_duration_text(&self) -> Option<String> {
+        self.completion_duration().map(format_duration)
+    }
+
+    pub fn created_ago_text(&self) -> String {
+        relative_time(self.created_at)
+    }
+
+    /// Text used to detect duplicates: trimmed, lowercased, single-spaced.
+    pub fn normalized_text(&self) -> String {
+        normalize_text(&self.text).to_lowercase()
+    }
+
+    pub fn quadrant(&self) -> TodoQuadrant {
+        match (self.is_urgent, self.is_important) {
+            (true, true) => TodoQuadrant::Do,
+            (false, true) => TodoQuadrant::Schedule,
+            (true, false) => TodoQuadrant::Delegate,
+            (false, false) => TodoQuadrant::Eliminate,
+        }
+    }
+}
+
+/// A checklist item within a todo. Unless `TodoRepo::auto_complete` is off (see
+/// `TODO_AUTO_COMPLETE_SUBTASKS`), completing every subtask completes the todo.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct Subtask {
+    pub id: Uuid,
+    pub text: String,
+    pub done: bool,
+}
+
+impl Subtask {
+    pub fn new(text: &str) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            text: String::from(text),
+            done: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TodoUpdate {
+    pub text: Option<String>,
+    /// Checks or unchecks the todo; unchecking leaves an `InProgress` todo as it is.
+    pub is_completed: Option<bool>,
+    /// Takes precedence over `is_completed`.
+    pub status: Option<TodoStatus>,
+    pub is_urgent: Option<bool>,
+    pub is_important: Option<bool>,
+    /// A blank description clears it.
+    pub description: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// `Some(None)` clears it.
+    pub recurrence: Option<Option<Recurrence>>,
+    /// `Some(None)` clears it.
+    pub due_at: Option<Option<SystemTime>>,
+    /// A blank color clears it; anything outside `COLORS` is rejected.
+    pub color: Option<String>,
+    /// Refuses the whole update unless the todo still has this revision.
+    pub expected_revision: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+/// What `TodoRepo::query` lists. Every part is applied at once, and left at its default it
+/// keeps everything `filter` matches.
+#[derive(Debug, Default, Clone)]
+pub struct QueryParams {
+    pub filter: TodoListFilter,
+    /// Case-insensitive part of the text; blank searches match every todo.
+    pub search: Option<String>,
+    /// Keeps only completed or only open todos, on top of `filter`.
+    pub completed: Option<bool>,
+    /// Keeps only todos with or only todos without a `due_at`.
+    pub has_due: Option<bool>,
+    /// Compared after normalization, so a tag that normalizes to nothing matches no todo.
+    pub tag: Option<String>,
+    pub sort: TodoSort,
+    /// Pages start below this cursor. Paged queries run newest first and ignore `sort`.
+    pub after: Option<TodoCursor>,
+    /// Page size; setting it pages the query even without `after`. 0 is taken as 1.
+    pub limit: Option<usize>,
+}
+
+pub const MAX_TEXT_LEN: usize = 256;
+
+/// A todo text must contain something other than whitespace and fit in `MAX_TEXT_LEN`.
+pub fn is_valid_text(text: &str) -> bool {
+    !text.trim().is_empty() && text.chars().count() <= MAX_TEXT_LEN
+}
+
+/// Trims `text` and collapses every run of whitespace inside it, newlines included, into a
+/// single space.
+pub fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub const MAX_DESCRIPTION_LEN: usize = 4096;
+
+pub fn is_valid_description(description: &str) -> bool {
+    description.chars().count() <= MAX_DESCRIPTION_LEN
+}
+
+/// Color labels a todo can carry.
+pub const COLORS: [&str; 4] = ["red", "green", "blue", "yellow"];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownColor(pub String);
+
+/// Lowercases a color label, returning `None` when blank.
+pub fn parse_color(raw: &str) -> Result<Option<String>, UnknownColor> {
+    let color = raw.trim().to_lowercase();
+
+    if color.is_empty() {
+        Ok(None)
+    } else if COLORS.contains(&color.as_str()) {
+        Ok(Some(color))
+    } else {
+        Err(UnknownColor(raw.to_string()))
+    }
+}
+
+/// Lowercases a single tag, returning `None` when nothing is left after trimming.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// Splits a comma-separated list of tags, dropping empty and repeated ones.
+pub fn parse_tags(raw: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+
+    for tag in raw.split(',').filter_map(normalize_tag) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    tags
+}
+
+/// Where a todo stands; serialized as the variant name, with the kebab-case `TodoListFilter`
+/// spelling as an alias.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum TodoStatus {
+    /// Not started yet.
+    #[default]
+    #[serde(alias = "todo")]
+    Todo,
+    /// `in_progress` is accepted too.
+    #[serde(alias = "in-progress", alias = "in_progress")]
+    InProgress,
+    #[serde(alias = "done")]
+    Done,
+}
+
+/// Which way into the app a todo was created through.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum TodoSource {
+    /// The page's forms and buttons.
+    #[default]
+    Web,
+    /// A JSON request or the WebSocket.
+    Api,
+    /// `POST /todo/import`.
+    Import,
+}
+
+/// Eisenhower matrix quadrant, in the order returned by `TodoRepo::by_quadrant`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoQuadrant {
+    /// Urgent and important.
+    Do,
+    /// Important but not urgent.
+    Schedule,
+    /// Urgent but not important.
+    Delegate,
+    /// Neither urgent nor important.
+    Eliminate,
+}
+
+/// Serialized as the variant name; the lowercase `Display` form used in
+/// htmx URLs is accepted as an alias.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
 pub enum TodoListFilter {
+    #[serde(alias = "completed")]
     Completed,
+    /// Open todos, `InProgress` ones included.
+    #[serde(alias = "active")]
     Active,
+    /// Started but not done. `in_progress` is accepted too, as for `TodoStatus`.
+    #[serde(alias = "in-progress", alias = "in_progress")]
+    InProgress,
+    #[default]
+    #[serde(alias = "all")]
     All,
+    /// Deleted todos that can still be restored; every other filter skips them.
+    #[serde(alias = "trash")]
+    Trash,
+    /// Created within the last day.
+    #[serde(alias = "today")]
+    Today,
+    /// Created within the last 7 days.
+    #[serde(alias = "this-week")]
+    ThisWeek,
+    /// Open todos whose due date has passed.
+    #[serde(alias = "overdue")]
+    Overdue,
+}
+
+impl TodoListFilter {
+    pub fn matches(&self, todo: &Todo) -> bool {
+        self.matches_at(todo, SystemTime::now())
+    }
+
+    /// Like `matches`, with `Today`, `ThisWeek` and `Overdue` measured at `now`.
+    pub fn matches_at(&self, todo: &Todo, now: SystemTime) -> bool {
+        match self {
+            Self::Completed => todo.deleted_at.is_none() && todo.is_completed(),
+            Self::Active => todo.deleted_at.is_none() && !todo.is_completed(),
+            Self::InProgress => todo.deleted_at.is_none() && todo.status == TodoStatus::InProgress,
+            Self::All => todo.deleted_at.is_none(),
+            Self::Trash => todo.deleted_at.is_some(),
+            Self::Today => todo.deleted_at.is_none() && is_created_within(todo, DAY, now),
+            Self::ThisWeek => todo.deleted_at.is_none() && is_created_within(todo, 7 * DAY, now),
+            Self::Overdue => todo.deleted_at.is_none() && todo.is_overdue_at(now),
+        }
+    }
+
+    /// Whether the todos it keeps can change with the clock alone.
+    pub fn depends_on_clock(&self) -> bool {
+        matches!(self, Self::Today | Self::ThisWeek | Self::Overdue)
+    }
+}
+
+/// Todos stamped after `now`, e.g. by a clock that stepped back, count as just created.
+fn is_created_within(todo: &Todo, window: Duration, now: SystemTime) -> bool {
+    now.duration_since(todo.created_at)
+        .map_or(true, |age| age < window)
 }
 
 impl fmt::Display for TodoListFilter {
@@ -33,17 +458,194 @@ impl fmt::Display for TodoListFilter {
         match self {
             Self::Completed => write!(f, "completed"),
             Self::Active => write!(f, "active"),
+            Self::InProgress => write!(f, "in-progress"),
             Self::All => write!(f, "all"),
+            Self::Trash => write!(f, "trash"),
+            Self::Today => write!(f, "today"),
+            Self::ThisWeek => write!(f, "this-week"),
+            Self::Overdue => write!(f, "overdue"),
+        }
+    }
+}
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often a completed todo comes back through `TodoRepo::roll_recurring`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum Recurrence {
+    #[serde(alias = "daily")]
+    Daily,
+    #[serde(alias = "weekly")]
+    Weekly,
+    /// Daily, skipping Saturdays and Sundays (UTC).
+    #[serde(alias = "weekdays")]
+    Weekdays,
+}
+
+impl Recurrence {
+    /// When a todo completed at `completed_at` is due again.
+    pub fn next_due(&self, completed_at: SystemTime) -> SystemTime {
+        match self {
+            Self::Daily => completed_at + DAY,
+            Self::Weekly => completed_at + 7 * DAY,
+            Self::Weekdays => {
+                let mut due = completed_at + DAY;
+
+                while is_weekend(due) {
+                    due += DAY;
+                }
+
+                due
+            }
+        }
+    }
+}
+
+fn is_weekend(time: SystemTime) -> bool {
+    let days = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / DAY.as_secs();
+
+    // The epoch fell on a Thursday, so Saturday is 2 days later.
+    matches!(days % 7, 2 | 3)
+}
+
+/// Order in which `TodoRepo::sorted_items` returns todos.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
+pub enum TodoSort {
+    /// Newest first, the default.
+    #[default]
+    #[serde(alias = "newest")]
+    Newest,
+    /// Oldest first.
+    #[serde(alias = "oldest")]
+    Oldest,
+    /// By `position`, as arranged with `TodoRepo::reorder`.
+    #[serde(alias = "manual")]
+    Manual,
+    /// Active todos first, then completed ones, each newest first.
+    #[serde(alias = "completed_last")]
+    CompletedLast,
+}
+
+/// Where a page of todos ended, written `<created_at as RFC 3339>,<id>`.
+///
+/// Pages run newest first with ties broken by id, so the next page holds the todos ordered
+/// strictly below the cursor. A bare timestamp is accepted too and skips every todo created at
+/// that moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TodoCursor {
+    pub created_at: SystemTime,
+    pub id: Uuid,
+}
+
+impl TodoCursor {
+    pub fn of(todo: &Todo) -> Self {
+        Self {
+            created_at: todo.created_at,
+            id: todo.id,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+impl fmt::Display for TodoCursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{}",
+            humantime::format_rfc3339_nanos(self.created_at),
+            self.id
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidCursor(pub String);
+
+impl FromStr for TodoCursor {
+    type Err = InvalidCursor;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCursor(raw.to_string());
+        let (created_at, id) = match raw.split_once(',') {
+            Some((created_at, id)) => (created_at, id.parse().map_err(|_| invalid())?),
+            None => (raw, Uuid::nil()),
+        };
+
+        Ok(Self {
+            created_at: humantime::parse_rfc3339(created_at).map_err(|_| invalid())?,
+            id,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for TodoCursor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        raw.parse()
+            .map_err(|_| serde::de::Error::custom(format!("invalid cursor: {}", raw)))
+    }
+}
+
+/// Which todo survives when `TodoRepo::deduplicate` finds identical texts.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TodoDedupKeep {
+    /// The oldest todo.
+    Oldest,
+    /// The oldest completed todo, falling back to the oldest one.
+    Completed,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownFilter(pub String);
+
+impl FromStr for TodoListFilter {
+    type Err = UnknownFilter;
+
+    /// Accepts both the variant names and their `Display` form, ignoring case and with
+    /// underscores for dashes.
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let dashed = raw.replace('_', "-");
+
+        [
+            Self::Completed,
+            Self::Active,
+            Self::InProgress,
+            Self::All,
+            Self::Trash,
+            Self::Today,
+            Self::ThisWeek,
+            Self::Overdue,
+        ]
+        .into_iter()
+        .find(|filter| filter.to_string().eq_ignore_ascii_case(&dashed))
+        .ok_or_else(|| UnknownFilter(raw.to_string()))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
 pub enum TodoToggleAction {
+    #[serde(alias = "uncheck")]
     Uncheck,
+    #[serde(alias = "check")]
     Check,
 }
 
+impl TodoToggleAction {
+    /// What toggling `items` at once should do: uncheck them when every one is completed.
+    pub fn for_items<'a>(items: impl IntoIterator<Item = &'a Todo>) -> Self {
+        let mut items = items.into_iter().peekable();
+
+        if items.peek().is_some() && items.all(Todo::is_completed) {
+            Self::Uncheck
+        } else {
+            Self::Check
+        }
+    }
+}
+
 impl fmt::Display for TodoToggleAction {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -52,3 +654,417 @@ impl fmt::Display for TodoToggleAction {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoEvent {
+    Created(Uuid),
+    Updated(Uuid),
+    Deleted(Uuid),
+    Restored(Uuid),
+    Toggled,
+    DeletedCompleted,
+    Cleared,
+    Deduplicated,
+    Imported,
+    Transaction,
+    Purged,
+}
+
+impl TodoEvent {
+    pub fn id(&self) -> Option<Uuid> {
+        match self {
+            Self::Created(id) | Self::Updated(id) | Self::Deleted(id) | Self::Restored(id) => {
+                Some(*id)
+            }
+            Self::Toggled
+            | Self::DeletedCompleted
+            | Self::Cleared
+            | Self::Deduplicated
+            | Self::Imported
+            | Self::Transaction
+            | Self::Purged => None,
+        }
+    }
+}
+
+impl fmt::Display for TodoEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Created(_) => write!(f, "todo-created"),
+            Self::Updated(_) => write!(f, "todo-updated"),
+            Self::Deleted(_) => write!(f, "todo-deleted"),
+            Self::Restored(_) => write!(f, "todo-restored"),
+            Self::Toggled => write!(f, "todo-toggled"),
+            Self::DeletedCompleted => write!(f, "todo-deleted-completed"),
+            Self::Cleared => write!(f, "todo-cleared"),
+            Self::Deduplicated => write!(f, "todo-deduplicated"),
+            Self::Imported => write!(f, "todo-imported"),
+            Self::Transaction => write!(f, "todo-transaction"),
+            Self::Purged => write!(f, "todo-purged"),
+        }
+    }
+}
+
+/// A form field that is either left out (`None`), sent blank to clear it (`Some(None)`) or set.
+pub(crate) mod clearable {
+    use serde::{de::IntoDeserializer, Deserialize, Deserializer};
+
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+
+        if raw.trim().is_empty() {
+            return Ok(Some(None));
+        }
+
+        T::deserialize(raw.into_deserializer()).map(|value| Some(Some(value)))
+    }
+}
+
+/// (De)serializes timestamps as RFC 3339 strings with nanosecond precision.
+pub(crate) mod rfc3339 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::time::SystemTime;
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&humantime::format_rfc3339_nanos(*time))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        humantime::parse_rfc3339(&raw).map_err(D::Error::custom)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::time::SystemTime;
+
+        pub fn serialize<S: Serializer>(
+            time: &Option<SystemTime>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match time {
+                Some(time) => super::serialize(time, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<SystemTime>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] SystemTime);
+
+            let wrapper = Option::<Wrapper>::deserialize(deserializer)?;
+            Ok(wrapper.map(|Wrapper(time)| time))
+        }
+    }
+
+    /// Like `super::clearable`, for timestamps.
+    pub mod clearable {
+        use serde::{de::Error, Deserialize, Deserializer};
+        use std::time::SystemTime;
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Option<SystemTime>>, D::Error> {
+            let raw = String::deserialize(deserializer)?;
+
+            if raw.trim().is_empty() {
+                return Ok(Some(None));
+            }
+
+            humantime::parse_rfc3339(&raw)
+                .map(|time| Some(Some(time)))
+                .map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_filter_serde() {
+        let cases = [
+            (TodoListFilter::Completed, "Completed", "completed"),
+            (TodoListFilter::Active, "Active", "active"),
+            (TodoListFilter::InProgress, "InProgress", "in-progress"),
+            (TodoListFilter::All, "All", "all"),
+            (TodoListFilter::Trash, "Trash", "trash"),
+            (TodoListFilter::Today, "Today", "today"),
+            (TodoListFilter::ThisWeek, "ThisWeek", "this-week"),
+            (TodoListFilter::Overdue, "Overdue", "overdue"),
+        ];
+
+        for (filter, name, alias) in cases {
+            assert_eq!(serde_json::to_value(filter).unwrap(), json!(name));
+            assert_eq!(
+                serde_json::from_value::<TodoListFilter>(json!(name)).unwrap(),
+                filter
+            );
+            assert_eq!(
+                serde_json::from_value::<TodoListFilter>(json!(alias)).unwrap(),
+                filter
+            );
+            assert_eq!(
+                serde_json::from_value::<TodoListFilter>(json!(filter.to_string())).unwrap(),
+                filter
+            );
+        }
+
+        assert_eq!(
+            serde_json::from_value::<TodoListFilter>(json!("in_progress")).unwrap(),
+            TodoListFilter::InProgress
+        );
+    }
+
+    #[test]
+    fn test_toggle_action_serde() {
+        let cases = [
+            (TodoToggleAction::Uncheck, "Uncheck", "uncheck"),
+            (TodoToggleAction::Check, "Check", "check"),
+        ];
+
+        for (action, name, alias) in cases {
+            assert_eq!(serde_json::to_value(action).unwrap(), json!(name));
+            assert_eq!(
+                serde_json::from_value::<TodoToggleAction>(json!(name)).unwrap(),
+                action
+            );
+            assert_eq!(
+                serde_json::from_value::<TodoToggleAction>(json!(alias)).unwrap(),
+                action
+            );
+            assert_eq!(
+                serde_json::from_value::<TodoToggleAction>(json!(action.to_string())).unwrap(),
+                action
+            );
+        }
+    }
+
+    #[test]
+    fn test_status_serde() {
+        let cases = [
+            (TodoStatus::Todo, "Todo", "todo"),
+            (TodoStatus::InProgress, "InProgress", "in-progress"),
+            (TodoStatus::Done, "Done", "done"),
+        ];
+
+        for (status, name, alias) in cases {
+            assert_eq!(serde_json::to_value(status).unwrap(), json!(name));
+            assert_eq!(
+                serde_json::from_value::<TodoStatus>(json!(name)).unwrap(),
+                status
+            );
+            assert_eq!(
+                serde_json::from_value::<TodoStatus>(json!(alias)).unwrap(),
+                status
+            );
+        }
+
+        assert_eq!(
+            serde_json::from_value::<TodoStatus>(json!("in_progress")).unwrap(),
+            TodoStatus::InProgress
+        );
+    }
+
+    #[test]
+    fn test_todo_legacy_is_completed_serde() {
+        let mut todo = Todo::new("a");
+        todo.status = TodoStatus::Done;
+
+        let value = serde_json::to_value(&todo).unwrap();
+
+        assert_eq!(value["status"], json!("Done"));
+        assert_eq!(value["is_completed"], json!(true));
+        assert_eq!(serde_json::from_value::<Todo>(value).unwrap(), todo);
+
+        let legacy = |fields: serde_json::Value| {
+            let mut value = json!({
+                "id": todo.id,
+                "text": "a",
+                "created_at": "2024-05-01T17:00:00Z",
+            });
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(fields.as_object().unwrap().clone());
+
+            serde_json::from_value::<Todo>(value).unwrap().status
+        };
+
+        assert_eq!(legacy(json!({ "is_completed": true })), TodoStatus::Done);
+        assert_eq!(legacy(json!({ "is_completed": false })), TodoStatus::Todo);
+        assert_eq!(legacy(json!({})), TodoStatus::Todo);
+        assert_eq!(
+            legacy(json!({ "is_completed": true, "status": "InProgress" })),
+            TodoStatus::InProgress
+        );
+    }
+
+    #[test]
+    fn test_dedup_keep_serde() {
+        assert_eq!(
+            serde_json::from_value::<TodoDedupKeep>(json!("Oldest")).unwrap(),
+            TodoDedupKeep::Oldest
+        );
+        assert_eq!(
+            serde_json::from_value::<TodoDedupKeep>(json!("Completed")).unwrap(),
+            TodoDedupKeep::Completed
+        );
+        assert!(serde_json::from_value::<TodoDedupKeep>(json!("Newest")).is_err());
+    }
+
+    #[test]
+    fn test_completion_duration() {
+        // Arrange
+        let mut todo = Todo::new("test");
+
+        todo.status = TodoStatus::Done;
+        todo.completed_at = Some(todo.created_at + Duration::from_secs(7200));
+
+        // Act
+        let result = todo.completion_duration();
+
+        // Assert
+        assert_eq!(result, Some(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn test_completion_duration_clock_skew() {
+        // Arrange
+        let mut todo = Todo::new("test");
+
+        todo.status = TodoStatus::Done;
+        todo.completed_at = Some(todo.created_at - Duration::from_secs(10));
+
+        // Act
+        let result = todo.completion_duration();
+
+        // Assert
+        assert_eq!(result, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_completion_duration_active() {
+        // Arrange
+        let todo = Todo::new("test");
+
+        // Act
+        let result = todo.completion_duration();
+
+        // Assert
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_parse_cursor() {
+        // Arrange
+        let created_at = SystemTime::UNIX_EPOCH + Duration::from_nanos(1_500_000_001);
+        let cursor = TodoCursor {
+            created_at,
+            id: Uuid::new_v4(),
+        };
+
+        // Act
+        let round_trip = cursor.to_string().parse::<TodoCursor>();
+        let bare = "1970-01-01T00:00:01.500000001Z".parse::<TodoCursor>();
+        let invalid = "yesterday".parse::<TodoCursor>();
+
+        // Assert
+        assert_eq!(round_trip, Ok(cursor));
+        assert_eq!(
+            bare,
+            Ok(TodoCursor {
+                created_at,
+                id: Uuid::nil()
+            })
+        );
+        assert_eq!(invalid, Err(InvalidCursor(String::from("yesterday"))));
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        // Arrange
+        let cases = [
+            ("completed", Ok(TodoListFilter::Completed)),
+            ("Active", Ok(TodoListFilter::Active)),
+            ("ALL", Ok(TodoListFilter::All)),
+            ("trash", Ok(TodoListFilter::Trash)),
+            ("This-Week", Ok(TodoListFilter::ThisWeek)),
+            ("in_progress", Ok(TodoListFilter::InProgress)),
+            ("OVERDUE", Ok(TodoListFilter::Overdue)),
+            ("archived", Err(UnknownFilter(String::from("archived")))),
+        ];
+
+        for (raw, expected) in cases {
+            // Act
+            let result = raw.parse::<TodoListFilter>();
+
+            // Assert
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        // Arrange
+        let raw = " Work,home, ,work,HOME ,errands,";
+
+        // Act
+        let result = parse_tags(raw);
+
+        // Assert
+        assert_eq!(result, vec!["work", "home", "errands"]);
+    }
+
+    #[test]
+    fn test_parse_tags_empty() {
+        // Arrange
+        let raw = " , ,";
+
+        // Act
+        let result = parse_tags(raw);
+
+        // Assert
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_recurrence_next_due() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        // 2024-01-05, a Friday.
+        let friday = humantime::parse_rfc3339("2024-01-05T09:00:00Z").unwrap();
+
+        assert_eq!(Recurrence::Daily.next_due(friday), friday + day);
+        assert_eq!(Recurrence::Weekly.next_due(friday), friday + 7 * day);
+        assert_eq!(Recurrence::Weekdays.next_due(friday), friday + 3 * day);
+        assert_eq!(Recurrence::Weekdays.next_due(friday - day), friday);
+    }
+
+    #[test]
+    fn test_parse_color() {
+        // Arrange
+        let cases = [
+            ("red", Ok(Some(String::from("red")))),
+            (" Blue ", Ok(Some(String::from("blue")))),
+            ("  ", Ok(None)),
+            ("purple", Err(UnknownColor(String::from("purple")))),
+        ];
+
+        for (raw, expected) in cases {
+            // Act
+            let result = parse_color(raw);
+
+            // Assert
+            assert_eq!(result, expected);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+/// Fixed-window request limiter keyed by client address.
+///
+/// Requests whose address is unknown share a single window.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    hits: HashMap<Option<IpAddr>, (Instant, u32)>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            hits: HashMap::new(),
+        }
+    }
+
+    /// Records a request and returns whether it is within the limit.
+    pub fn check(&mut self, client: Option<IpAddr>, now: Instant) -> bool {
+        let window = self.window;
+
+        self.hits
+            .retain(|_, (started, _)| now.duration_since(*started) < window);
+
+        let (_, count) = self.hits.entry(client).or_insert((now, 0));
+
+        if *count >= self.limit {
+            return false;
+        }
+
+        *count += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_check() {
+        // Arrange
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let client = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let other = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+        let now = Instant::now();
+
+        // Act & Assert
+        assert!(limiter.check(client, now));
+        assert!(limiter.check(client, now));
+        assert!(!limiter.check(client, now));
+        assert!(limiter.check(other, now));
+        assert!(limiter.check(client, now + Duration::from_secs(60)));
+    }
+}
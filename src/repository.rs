@@ -1,500 +1,3585 @@
-use crate::models::{Todo, TodoListFilter, TodoToggleAction};
-use std::collections::HashMap;
+use crate::models::{
+    is_valid_text, normalize_tag, normalize_text, parse_color, QueryParams, Subtask, Todo,
+    TodoCursor, TodoDedupKeep, TodoListFilter, TodoSort, TodoSource, TodoStatus, TodoToggleAction,
+    TodoUpdate, MAX_TEXT_LEN,
+};
+use crate::util::{decode_base62, substring_distance};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
 use uuid::Uuid;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum TodoRepoError {
     NotFound,
+    /// An active todo with the same trimmed text already exists.
+    Duplicate,
+    /// The repo already holds as many todos as its capacity allows.
+    CapacityReached,
+    /// The color label is not one of `models::COLORS`.
+    InvalidColor,
+    /// The todo's revision differs from the one the caller expected.
+    Conflict,
+    /// An update tried to replace the text with nothing but whitespace.
+    BlankText,
+    /// The text would grow past `models::MAX_TEXT_LEN`.
+    TextTooLong,
+    /// A single tag was expected but a comma, which separates tags, was found.
+    InvalidTag,
 }
 
-#[derive(Debug, Default)]
+/// Put between a todo's text and what `TodoRepo::append_text` adds.
+pub const APPEND_SEPARATOR: &str = "; ";
+
+/// A todo as stored after `TodoRepo::update`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateOutcome {
+    pub todo: Todo,
+    /// Whether the update moved the todo into or out of `Done`.
+    pub completion_changed: bool,
+}
+
+/// Todos returned by `TodoRepo::list_after`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoPage {
+    pub items: Vec<Todo>,
+    /// Where the next page starts; `None` once nothing is left.
+    pub next: Option<TodoCursor>,
+}
+
+#[derive(Debug, Clone)]
 pub struct TodoRepo {
     pub num_completed_items: u32,
+    /// Not done, so also part of `num_active_items`.
+    pub num_in_progress_items: u32,
     pub num_active_items: u32,
     pub num_all_items: u32,
+    /// Whether `create` and `update` pass texts through `models::normalize_text`; on by default.
+    pub normalize: bool,
+    /// Order of `list`, and of rendered lists that ask for no order in particular.
+    pub default_sort: TodoSort,
+    /// Whether a todo with subtasks is completed once all of them are done, and reopened when
+    /// one is open again; on by default.
+    pub auto_complete: bool,
     items: HashMap<Uuid, Todo>,
+    next_seq: u64,
+    capacity: Option<usize>,
+    version: u64,
+    id_source: IdSource,
+}
+
+impl Default for TodoRepo {
+    fn default() -> Self {
+        Self {
+            num_completed_items: 0,
+            num_in_progress_items: 0,
+            num_active_items: 0,
+            num_all_items: 0,
+            normalize: true,
+            default_sort: TodoSort::default(),
+            auto_complete: true,
+            items: HashMap::new(),
+            next_seq: 0,
+            capacity: None,
+            version: 0,
+            id_source: IdSource::default(),
+        }
+    }
+}
+
+/// Generates the ids of new todos, `Uuid::new_v4` unless set by `TodoRepo::with_id_source`.
+///
+/// Clones of the repo share the same source.
+#[derive(Clone, Default)]
+struct IdSource(Option<Arc<Mutex<dyn FnMut() -> Uuid + Send>>>);
+
+impl IdSource {
+    fn next(&self) -> Uuid {
+        match &self.0 {
+            Some(source) => (source.lock().unwrap_or_else(PoisonError::into_inner))(),
+            None => Uuid::new_v4(),
+        }
+    }
+}
+
+impl fmt::Debug for IdSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("IdSource(..)")
+    }
 }
 
 impl TodoRepo {
+    /// An empty repo that refuses to grow beyond `capacity` todos.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Default::default()
+        }
+    }
+
+    /// An empty repo listing todos in `sort` order unless told otherwise.
+    pub fn with_sort(sort: TodoSort) -> Self {
+        Self {
+            default_sort: sort,
+            ..Default::default()
+        }
+    }
+
+    /// An empty repo drawing the ids of new todos from `source`, e.g. a counter in tests.
+    pub fn with_id_source(source: impl FnMut() -> Uuid + Send + 'static) -> Self {
+        Self {
+            id_source: IdSource(Some(Arc::new(Mutex::new(source)))),
+            ..Default::default()
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        // Trashed todos do not count, so deleting one always frees a slot.
+        self.capacity.is_some_and(|capacity| {
+            self.items
+                .values()
+                .filter(|todo| todo.deleted_at.is_none())
+                .count()
+                >= capacity
+        })
+    }
+
+    pub fn counters(&self) -> (u32, u32, u32) {
+        (
+            self.num_completed_items,
+            self.num_active_items,
+            self.num_all_items,
+        )
+    }
+
+    /// Percentage of todos completed, rounded down, and 0 for an empty repo.
+    pub fn completion_pct(&self) -> u8 {
+        match self.num_all_items {
+            0 => 0,
+            all => (u64::from(self.num_completed_items) * 100 / u64::from(all)) as u8,
+        }
+    }
+
+    /// One-line status such as "3 active, 2 done (5 total)", or "no todos" when empty.
+    pub fn summary_line(&self) -> String {
+        match self.num_all_items {
+            0 => String::from("no todos"),
+            all => format!(
+                "{} active, {} done ({} total)",
+                self.num_active_items, self.num_completed_items, all
+            ),
+        }
+    }
+
+    /// Number of changes made so far, bumped by every mutation that changes something.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Trashed todos are not found, here and everywhere else but `restore` and `purge`.
     pub fn get(&self, id: &Uuid) -> Result<Todo, TodoRepoError> {
-        self.items.get(id).cloned().ok_or(TodoRepoError::NotFound)
+        self.items
+            .get(id)
+            .filter(|todo| todo.deleted_at.is_none())
+            .cloned()
+            .ok_or(TodoRepoError::NotFound)
+    }
+
+    /// Looks a todo up by `Todo::short_id`.
+    pub fn get_by_short(&self, short: &str) -> Result<Todo, TodoRepoError> {
+        let seq = decode_base62(short).ok_or(TodoRepoError::NotFound)?;
+
+        self.items
+            .values()
+            .find(|todo| todo.seq == seq && todo.deleted_at.is_none())
+            .cloned()
+            .ok_or(TodoRepoError::NotFound)
+    }
+
+    /// Like `get`, for changing the todo in place.
+    fn get_mut(&mut self, id: &Uuid) -> Result<&mut Todo, TodoRepoError> {
+        self.items
+            .get_mut(id)
+            .filter(|todo| todo.deleted_at.is_none())
+            .ok_or(TodoRepoError::NotFound)
     }
 
     pub fn list(&self, filter: &TodoListFilter) -> Vec<Todo> {
+        self.sorted_items(filter, self.default_sort)
+    }
+
+    /// Like `list`, with `Today`, `ThisWeek` and `Overdue` measured at `now`.
+    pub fn list_at(&self, filter: &TodoListFilter, now: SystemTime) -> Vec<Todo> {
+        self.sorted_items_at(filter, self.default_sort, now)
+    }
+
+    /// Lists the todos matching `filter` in `sort` order. Every rendered list goes through here,
+    /// so todos shared by two filters or endpoints always keep their relative order.
+    pub fn sorted_items(&self, filter: &TodoListFilter, sort: TodoSort) -> Vec<Todo> {
+        self.sorted_items_at(filter, sort, SystemTime::now())
+    }
+
+    fn sorted_items_at(
+        &self,
+        filter: &TodoListFilter,
+        sort: TodoSort,
+        now: SystemTime,
+    ) -> Vec<Todo> {
         let mut todos = self
             .items
             .values()
-            .filter(|item| match filter {
-                TodoListFilter::Completed => item.is_completed,
-                TodoListFilter::Active => !item.is_completed,
-                TodoListFilter::All => true,
-            })
+            .filter(|item| filter.matches_at(item, now))
             .cloned()
             .collect::<Vec<_>>();
 
-        todos.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        match sort {
+            TodoSort::Newest => sort_newest_first(&mut todos),
+            TodoSort::Oldest => sort_oldest_first(&mut todos),
+            TodoSort::Manual => sort_manual(&mut todos),
+            TodoSort::CompletedLast => sort_completed_last(&mut todos),
+        }
         todos
     }
 
-    pub fn create(&mut self, text: &str) -> Todo {
-        let todo = Todo::new(text);
+    /// Lists up to `limit` todos ordered below `after`, newest first with ties broken by id.
+    ///
+    /// Without `after`, the page starts at the newest todo. A `limit` of 0 is taken as 1.
+    pub fn list_after(
+        &self,
+        filter: &TodoListFilter,
+        after: Option<TodoCursor>,
+        limit: usize,
+    ) -> TodoPage {
+        self.query(&QueryParams {
+            filter: *filter,
+            after,
+            limit: Some(limit),
+            ..Default::default()
+        })
+    }
+
+    /// Lists the todos matching every part of `params` in one pass.
+    pub fn query(&self, params: &QueryParams) -> TodoPage {
+        let tag = params.tag.as_deref().map(normalize_tag);
+        let search = params
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|search| !search.is_empty())
+            .map(str::to_lowercase);
+        let matches = |todo: &Todo| {
+            tag.as_ref()
+                .is_none_or(|tag| tag.as_ref().is_some_and(|tag| todo.tags.contains(tag)))
+                && params
+                    .completed
+                    .is_none_or(|completed| todo.is_completed() == completed)
+                && params
+                    .has_due
+                    .is_none_or(|has_due| todo.due_at.is_some() == has_due)
+                && search
+                    .as_ref()
+                    .is_none_or(|search| todo.text.to_lowercase().contains(search))
+        };
+
+        if params.after.is_none() && params.limit.is_none() {
+            let mut items = self.sorted_items(&params.filter, params.sort);
+            items.retain(matches);
+
+            return TodoPage { items, next: None };
+        }
+
+        let mut todos = self
+            .items
+            .values()
+            .filter(|item| params.filter.matches(item) && matches(item))
+            .filter(|item| {
+                params
+                    .after
+                    .is_none_or(|after| TodoCursor::of(item) < after)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        todos.sort_by_key(|todo| Reverse(TodoCursor::of(todo)));
+
+        let limit = params.limit.unwrap_or(usize::MAX).max(1);
+        let next = (todos.len() > limit).then(|| TodoCursor::of(&todos[limit - 1]));
+        todos.truncate(limit);
+
+        TodoPage { items: todos, next }
+    }
+
+    /// Like `query`, but keeps the todos within a few typos of `params.search`, one more allowed
+    /// for every 4 characters searched, closest first. Paging is ignored.
+    pub fn search_fuzzy(&self, params: &QueryParams) -> Vec<Todo> {
+        let search = params
+            .search
+            .as_deref()
+            .map(str::trim)
+            .filter(|search| !search.is_empty())
+            .map(str::to_lowercase);
+        let todos = self
+            .query(&QueryParams {
+                search: None,
+                after: None,
+                limit: None,
+                ..params.clone()
+            })
+            .items;
+
+        let Some(search) = search else {
+            return todos;
+        };
+        let max_distance = search.chars().count() / 4;
+
+        let mut matches = todos
+            .into_iter()
+            .filter_map(|todo| {
+                let distance = substring_distance(&search, &todo.text.to_lowercase());
+                (distance <= max_distance).then_some((distance, todo))
+            })
+            .collect::<Vec<_>>();
+
+        // Stable, so todos as close as each other keep `params.sort` order.
+        matches.sort_by_key(|(distance, _)| *distance);
+        matches.into_iter().map(|(_, todo)| todo).collect()
+    }
+
+    pub fn create(&mut self, text: &str) -> Result<Todo, TodoRepoError> {
+        self.create_from(text, TodoSource::Web)
+    }
+
+    /// Like `create`, recording that the todo came from `source`.
+    pub fn create_from(&mut self, text: &str, source: TodoSource) -> Result<Todo, TodoRepoError> {
+        if self.is_full() {
+            return Err(TodoRepoError::CapacityReached);
+        }
+
+        let mut todo = Todo::new(text);
+
+        if self.normalize {
+            todo.text = normalize_text(text);
+        }
+
+        check_text(&todo.text)?;
+
+        todo.id = self.id_source.next();
+        todo.source = source;
+        todo.seq = self.next_seq;
+        todo.position = self.next_position();
+        self.next_seq += 1;
 
         self.items.insert(todo.id, todo.clone());
-        self.num_active_items += 1;
-        self.num_all_items += 1;
+        self.version += 1;
+        self.recount();
 
-        todo
+        Ok(todo)
     }
 
-    pub fn delete(&mut self, id: &Uuid) -> Result<(), TodoRepoError> {
-        let item = self.items.remove(id).ok_or(TodoRepoError::NotFound)?;
+    /// Creates a todo per trimmed text, skipping blank and invalid ones as well as duplicates
+    /// (see `create_unique`) and stopping at the capacity.
+    pub fn create_many(&mut self, texts: &[String], source: TodoSource) -> Vec<Todo> {
+        let mut created = Vec::new();
+
+        for text in texts
+            .iter()
+            .map(|text| text.trim())
+            .filter(|text| is_valid_text(text))
+        {
+            match self.create_unique_from(text, source) {
+                Ok(todo) => created.push(todo),
+                Err(TodoRepoError::Duplicate) => continue,
+                Err(_) => break,
+            }
+        }
+
+        created
+    }
+
+    /// Like `create`, but refuses a text matching an active todo once trimmed, and normalized
+    /// when `normalize` is on.
+    pub fn create_unique(&mut self, text: &str) -> Result<Todo, TodoRepoError> {
+        self.create_unique_from(text, TodoSource::Web)
+    }
 
-        if item.is_completed {
-            self.num_completed_items -= 1;
+    /// Like `create_unique`, recording that the todo came from `source`.
+    pub fn create_unique_from(
+        &mut self,
+        text: &str,
+        source: TodoSource,
+    ) -> Result<Todo, TodoRepoError> {
+        // Compared as `create_from` would store it, since stored texts are normalized.
+        let stored = if self.normalize {
+            normalize_text(text)
         } else {
-            self.num_active_items -= 1;
+            String::from(text.trim())
+        };
+        let is_duplicate = self
+            .items
+            .values()
+            .any(|todo| TodoListFilter::Active.matches(todo) && todo.text.trim() == stored);
+
+        if is_duplicate {
+            return Err(TodoRepoError::Duplicate);
+        }
+
+        self.create_from(text, source)
+    }
+
+    /// Inserts todos keeping their ids and timestamps, skipping known ids, invalid texts and
+    /// anything beyond the capacity.
+    ///
+    /// Returns how many todos were imported and how many were skipped.
+    pub fn import(&mut self, todos: Vec<Todo>) -> (usize, usize) {
+        let mut imported = 0;
+        let mut skipped = 0;
+
+        for mut todo in todos {
+            if self.is_full() || self.items.contains_key(&todo.id) || !is_valid_text(&todo.text) {
+                skipped += 1;
+                continue;
+            }
+
+            todo.source = TodoSource::Import;
+            todo.seq = self.next_seq;
+            todo.position = self.next_position();
+            self.next_seq += 1;
+
+            self.items.insert(todo.id, todo);
+            imported += 1;
+        }
+
+        if imported > 0 {
+            self.version += 1;
         }
 
-        self.num_all_items -= 1;
+        self.recount();
+        (imported, skipped)
+    }
+
+    /// Moves the todo `id` to the trash, from where `restore` brings it back and `purge` drops
+    /// it for good.
+    pub fn delete(&mut self, id: &Uuid) -> Result<(), TodoRepoError> {
+        let todo = self.get_mut(id)?;
+
+        todo.deleted_at = Some(SystemTime::now());
+        todo.touch();
+        self.version += 1;
+        self.recount();
 
         Ok(())
     }
 
+    /// Takes the todo `id` back out of the trash.
+    pub fn restore(&mut self, id: &Uuid) -> Result<Todo, TodoRepoError> {
+        let todo = self
+            .items
+            .get_mut(id)
+            .filter(|todo| todo.deleted_at.is_some())
+            .ok_or(TodoRepoError::NotFound)?;
+
+        todo.deleted_at = None;
+        todo.touch();
+
+        let todo = todo.clone();
+        self.version += 1;
+        self.recount();
+
+        Ok(todo)
+    }
+
+    /// Creates an open copy of the todo `id` with its text, priority, description, tags, color
+    /// and subtasks, the subtasks unticked.
+    pub fn duplicate(&mut self, id: &Uuid) -> Result<Todo, TodoRepoError> {
+        let original = self.get(id)?;
+        let mut todo = self.create(&original.text)?;
+
+        todo.is_urgent = original.is_urgent;
+        todo.is_important = original.is_important;
+        todo.description = original.description;
+        todo.tags = original.tags;
+        todo.color = original.color;
+        todo.subtasks = original
+            .subtasks
+            .iter()
+            .map(|subtask| Subtask::new(&subtask.text))
+            .collect();
+
+        self.items.insert(todo.id, todo.clone());
+
+        Ok(todo)
+    }
+
+    /// Permanently removes every trashed todo, returning how many were removed.
+    pub fn purge(&mut self) -> usize {
+        let len = self.items.len();
+        self.items.retain(|_, todo| todo.deleted_at.is_none());
+
+        let purged = len - self.items.len();
+
+        if purged > 0 {
+            self.version += 1;
+        }
+
+        purged
+    }
+
+    pub fn by_quadrant(&self) -> [Vec<Todo>; 4] {
+        let mut quadrants: [Vec<Todo>; 4] = Default::default();
+
+        for todo in self.list(&TodoListFilter::All) {
+            quadrants[todo.quadrant() as usize].push(todo);
+        }
+
+        quadrants
+    }
+
+    /// Completions per hour within the trailing `window` ending at `now`.
+    pub fn velocity(&self, window: Duration, now: SystemTime) -> f64 {
+        let hours = window.as_secs_f64() / 3600.0;
+
+        if hours == 0.0 {
+            return 0.0;
+        }
+
+        let num_completed = self
+            .items
+            .values()
+            .filter(|todo| todo.deleted_at.is_none())
+            .filter_map(|todo| todo.completed_at)
+            .filter(|completed_at| {
+                now.duration_since(*completed_at)
+                    .is_ok_and(|elapsed| elapsed <= window)
+            })
+            .count();
+
+        num_completed as f64 / hours
+    }
+
     pub fn update(
         &mut self,
         id: &Uuid,
-        text: Option<String>,
-        is_completed: Option<bool>,
-    ) -> Result<Todo, TodoRepoError> {
-        let todo = self.items.get_mut(id).ok_or(TodoRepoError::NotFound)?;
+        update: TodoUpdate,
+    ) -> Result<UpdateOutcome, TodoRepoError> {
+        let TodoUpdate {
+            text,
+            is_completed,
+            status,
+            is_urgent,
+            is_important,
+            description,
+            tags,
+            recurrence,
+            due_at,
+            color,
+            expected_revision,
+        } = update;
+
+        let color = color
+            .as_deref()
+            .map(parse_color)
+            .transpose()
+            .map_err(|_| TodoRepoError::InvalidColor)?;
+
+        let text = text.map(|text| {
+            if self.normalize {
+                normalize_text(&text)
+            } else {
+                text
+            }
+        });
 
-        if let Some(is_completed) = is_completed {
-            todo.is_completed = is_completed;
+        if let Some(text) = &text {
+            check_text(text)?;
+        }
 
-            if todo.is_completed {
-                self.num_completed_items += 1;
-                self.num_active_items -= 1;
-            } else {
-                self.num_completed_items -= 1;
-                self.num_active_items += 1;
+        let todo = self.get_mut(id)?;
+
+        if expected_revision.is_some_and(|revision| revision != todo.revision) {
+            return Err(TodoRepoError::Conflict);
+        }
+
+        let before = todo.clone();
+
+        match (status, is_completed) {
+            (Some(status), _) if status != todo.status => todo.set_status(status),
+            (None, Some(is_completed)) if is_completed != todo.is_completed() => {
+                todo.set_completed(is_completed)
             }
+            _ => {}
         }
 
         if let Some(text) = text {
             todo.text = text;
         }
 
-        Ok(todo.clone())
+        if let Some(is_urgent) = is_urgent {
+            todo.is_urgent = is_urgent;
+        }
+
+        if let Some(is_important) = is_important {
+            todo.is_important = is_important;
+        }
+
+        if let Some(description) = description {
+            todo.description =
+                Some(description).filter(|description| !description.trim().is_empty());
+        }
+
+        if let Some(tags) = tags {
+            todo.tags = tags;
+        }
+
+        if let Some(recurrence) = recurrence {
+            todo.recurrence = recurrence;
+        }
+
+        if let Some(due_at) = due_at {
+            todo.due_at = due_at;
+        }
+
+        if let Some(color) = color {
+            todo.color = color;
+        }
+
+        let is_changed = *todo != before;
+
+        if is_changed {
+            todo.touch();
+        }
+
+        let outcome = UpdateOutcome {
+            todo: todo.clone(),
+            completion_changed: todo.is_completed() != before.is_completed(),
+        };
+
+        if is_changed {
+            self.version += 1;
+        }
+        self.recount();
+
+        Ok(outcome)
     }
 
-    pub fn delete_completed(&mut self) {
-        self.items.retain(|_, todo| !todo.is_completed);
-        self.num_all_items -= self.num_completed_items;
-        self.num_completed_items = 0;
+    /// The toggle-all action matching the todos `filter` shows.
+    pub fn toggle_action(&self, filter: &TodoListFilter) -> TodoToggleAction {
+        TodoToggleAction::for_items(self.items.values().filter(|item| filter.matches(item)))
+    }
+
+    /// Sets the completion state of a single todo, doing nothing if it already matches.
+    pub fn set_completed(
+        &mut self,
+        id: &Uuid,
+        is_completed: bool,
+    ) -> Result<UpdateOutcome, TodoRepoError> {
+        self.update(
+            id,
+            TodoUpdate {
+                is_completed: Some(is_completed),
+                ..Default::default()
+            },
+        )
     }
 
-    pub fn toggle_completed(&mut self, action: &TodoToggleAction) {
-        let is_completed: bool;
+    /// Sets the completion state of the listed todos, returning the ids of those that changed.
+    /// Unknown, trashed and already matching todos are skipped, so repeating a call is a no-op.
+    pub fn set_completed_many(&mut self, ids: &[Uuid], is_completed: bool) -> Vec<Uuid> {
+        let mut changed = Vec::new();
 
-        match action {
-            TodoToggleAction::Uncheck => {
-                self.num_completed_items = 0;
-                self.num_active_items = self.num_all_items;
+        for id in ids {
+            let Some(todo) = self.items.get_mut(id) else {
+                continue;
+            };
 
-                is_completed = false;
+            if TodoListFilter::All.matches(todo) && todo.is_completed() != is_completed {
+                todo.set_completed(is_completed);
+                todo.touch();
+                changed.push(*id);
             }
-            TodoToggleAction::Check => {
-                self.num_completed_items = self.num_all_items;
-                self.num_active_items = 0;
+        }
+
+        if !changed.is_empty() {
+            self.version += 1;
+        }
+
+        self.recount();
+        changed
+    }
+
+    /// Adds `tag`, once normalized, to the listed todos, returning the ids of those that changed.
+    /// Unknown, trashed and already tagged todos are skipped, as is every todo when `tag`
+    /// normalizes to nothing. Fails on a `tag` holding a comma, as that would be several tags.
+    pub fn add_tag_many(&mut self, ids: &[Uuid], tag: &str) -> Result<Vec<Uuid>, TodoRepoError> {
+        self.retag_many(ids, tag, |tags, tag| {
+            let is_new = !tags.contains(tag);
 
-                is_completed = true;
+            if is_new {
+                tags.push(tag.clone());
             }
+
+            is_new
+        })
+    }
+
+    /// Removes `tag`, once normalized, from the listed todos, returning the ids of those that
+    /// changed. Skips the same todos and rejects the same tags as `add_tag_many`.
+    pub fn remove_tag_many(&mut self, ids: &[Uuid], tag: &str) -> Result<Vec<Uuid>, TodoRepoError> {
+        self.retag_many(ids, tag, |tags, tag| {
+            let len = tags.len();
+            tags.retain(|other| other != tag);
+
+            tags.len() != len
+        })
+    }
+
+    fn retag_many(
+        &mut self,
+        ids: &[Uuid],
+        tag: &str,
+        mut retag: impl FnMut(&mut Vec<String>, &String) -> bool,
+    ) -> Result<Vec<Uuid>, TodoRepoError> {
+        if tag.contains(',') {
+            return Err(TodoRepoError::InvalidTag);
+        }
+
+        let Some(tag) = normalize_tag(tag) else {
+            return Ok(Vec::new());
         };
+        let mut changed = Vec::new();
 
-        for todo in self.items.values_mut() {
-            todo.is_completed = is_completed;
+        for id in ids {
+            let Some(todo) = self.items.get_mut(id) else {
+                continue;
+            };
+
+            if todo.deleted_at.is_none() && retag(&mut todo.tags, &tag) {
+                todo.touch();
+                changed.push(*id);
+            }
         }
+
+        if !changed.is_empty() {
+            self.version += 1;
+        }
+
+        Ok(changed)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Reopens every completed todo, returning how many were reopened.
+    pub fn uncomplete_all(&mut self) -> usize {
+        let completed = self
+            .items
+            .values()
+            .filter(|todo| TodoListFilter::Completed.matches(todo))
+            .map(|todo| todo.id)
+            .collect::<Vec<_>>();
 
-    #[test]
-    fn test_get_non_existing_todo() {
-        // Arrange
-        let repo = TodoRepo::default();
-        let id = Uuid::new_v4();
+        self.set_completed_many(&completed, false).len()
+    }
+
+    /// Removes every todo, completed or not.
+    pub fn clear(&mut self) {
+        if !self.items.is_empty() {
+            self.version += 1;
+        }
+
+        self.items.clear();
+        self.recount();
+    }
+
+    pub fn delete_completed(&mut self) {
+        let len = self.items.len();
+        self.items
+            .retain(|_, todo| !TodoListFilter::Completed.matches(todo));
+
+        if self.items.len() != len {
+            self.version += 1;
+        }
+        self.recount();
+    }
+
+    /// Removes completed todos that were completed more than `age` before `now`, returning how
+    /// many were removed. Trashed todos are left to the trash.
+    pub fn purge_completed_older_than(&mut self, age: Duration, now: SystemTime) -> u32 {
+        let len = self.items.len();
+        self.items.retain(|_, todo| {
+            let is_old = todo
+                .completed_at
+                .and_then(|completed_at| now.duration_since(completed_at).ok())
+                .is_some_and(|completed_for| completed_for > age);
+
+            !(TodoListFilter::Completed.matches(todo) && is_old)
+        });
+
+        let removed = len - self.items.len();
+
+        if removed > 0 {
+            self.version += 1;
+        }
+
+        self.recount();
+        removed as u32
+    }
+
+    /// Removes todos whose normalized text repeats, returning how many were removed.
+    pub fn deduplicate(&mut self, keep: TodoDedupKeep) -> usize {
+        let mut todos = self
+            .items
+            .values()
+            .filter(|todo| todo.deleted_at.is_none())
+            .collect::<Vec<_>>();
+        todos.sort_by_key(|todo| (todo.created_at, todo.seq));
+
+        let mut survivors: HashMap<String, &Todo> = HashMap::new();
+        let mut removed = Vec::new();
+
+        for todo in todos {
+            match survivors.get_mut(&todo.normalized_text()) {
+                None => {
+                    survivors.insert(todo.normalized_text(), todo);
+                }
+                Some(survivor)
+                    if keep == TodoDedupKeep::Completed
+                        && todo.is_completed()
+                        && !survivor.is_completed() =>
+                {
+                    removed.push(survivor.id);
+                    *survivor = todo;
+                }
+                Some(_) => removed.push(todo.id),
+            }
+        }
+
+        for id in &removed {
+            self.items.remove(id);
+        }
+
+        if !removed.is_empty() {
+            self.version += 1;
+        }
+
+        self.recount();
+        removed.len()
+    }
+
+    /// Checks or unchecks every todo matching `filter`, leaving the rest untouched.
+    pub fn toggle_completed(&mut self, action: &TodoToggleAction, filter: &TodoListFilter) {
+        let is_completed = match action {
+            TodoToggleAction::Uncheck => false,
+            TodoToggleAction::Check => true,
+        };
+
+        let mut is_changed = false;
+
+        for todo in self.items.values_mut() {
+            if filter.matches(todo) && todo.is_completed() != is_completed {
+                todo.set_completed(is_completed);
+                todo.touch();
+                is_changed = true;
+            }
+        }
+
+        if is_changed {
+            self.version += 1;
+        }
+
+        self.recount();
+    }
+
+    /// Creates a fresh, uncompleted copy of every completed recurring todo that is due again at
+    /// `now`, returning the new todos. The recurrence moves to the copy so each completion rolls
+    /// over only once.
+    pub fn roll_recurring(&mut self, now: SystemTime) -> Vec<Todo> {
+        let mut due = self
+            .items
+            .values()
+            .filter(|todo| {
+                let (Some(recurrence), Some(completed_at)) = (todo.recurrence, todo.completed_at)
+                else {
+                    return false;
+                };
+
+                TodoListFilter::Completed.matches(todo) && recurrence.next_due(completed_at) <= now
+            })
+            .map(|todo| todo.id)
+            .collect::<Vec<_>>();
+        due.sort_by_key(|id| self.items[id].seq);
+
+        let mut rolled = Vec::new();
+
+        for id in due {
+            if self.is_full() {
+                break;
+            }
+
+            let new_id = self.id_source.next();
+            let previous = self.items.get_mut(&id).unwrap();
+            let mut todo = Todo::new(&previous.text);
+
+            todo.id = new_id;
+            todo.is_urgent = previous.is_urgent;
+            todo.is_important = previous.is_important;
+            todo.description = previous.description.clone();
+            todo.tags = previous.tags.clone();
+            todo.subtasks = previous
+                .subtasks
+                .iter()
+                .map(|subtask| Subtask::new(&subtask.text))
+                .collect();
+            todo.recurrence = previous.recurrence.take();
+            todo.source = previous.source;
+            previous.touch();
+
+            todo.seq = self.next_seq;
+            todo.position = self.next_position();
+            self.next_seq += 1;
+
+            self.items.insert(todo.id, todo.clone());
+            rolled.push(todo);
+        }
+
+        if !rolled.is_empty() {
+            self.version += 1;
+        }
+        self.recount();
+        rolled
+    }
+
+    /// Adds `suffix` to the end of the todo's text, after `APPEND_SEPARATOR`.
+    pub fn append_text(&mut self, id: &Uuid, suffix: &str) -> Result<UpdateOutcome, TodoRepoError> {
+        let suffix = suffix.trim();
+
+        if suffix.is_empty() {
+            return Err(TodoRepoError::BlankText);
+        }
+
+        let todo = self.get_mut(id)?;
+        let text = format!("{}{}{}", todo.text, APPEND_SEPARATOR, suffix);
+
+        if text.chars().count() > MAX_TEXT_LEN {
+            return Err(TodoRepoError::TextTooLong);
+        }
+
+        todo.text = text;
+        todo.touch();
+
+        let outcome = UpdateOutcome {
+            todo: todo.clone(),
+            completion_changed: false,
+        };
+        self.version += 1;
+
+        Ok(outcome)
+    }
+
+    /// Appends a subtask to the todo `id`.
+    pub fn add_subtask(&mut self, id: &Uuid, text: &str) -> Result<UpdateOutcome, TodoRepoError> {
+        check_text(text)?;
+
+        let auto_complete = self.auto_complete;
+        let todo = self.get_mut(id)?;
+
+        todo.subtasks.push(Subtask::new(text));
+        todo.touch();
+
+        let outcome = UpdateOutcome {
+            completion_changed: auto_complete && follow_subtasks(todo),
+            todo: todo.clone(),
+        };
+        self.version += 1;
+        self.recount();
+
+        Ok(outcome)
+    }
+
+    /// Flips a subtask. Under `auto_complete`, its todo is completed once every subtask is done
+    /// and reopened when one is unchecked.
+    pub fn toggle_subtask(
+        &mut self,
+        id: &Uuid,
+        subtask_id: &Uuid,
+    ) -> Result<UpdateOutcome, TodoRepoError> {
+        let auto_complete = self.auto_complete;
+        let todo = self.get_mut(id)?;
+        let subtask = todo
+            .subtasks
+            .iter_mut()
+            .find(|subtask| subtask.id == *subtask_id)
+            .ok_or(TodoRepoError::NotFound)?;
+
+        subtask.done = !subtask.done;
+        todo.touch();
+
+        let outcome = UpdateOutcome {
+            completion_changed: auto_complete && follow_subtasks(todo),
+            todo: todo.clone(),
+        };
+        self.version += 1;
+        self.recount();
+
+        Ok(outcome)
+    }
+
+    /// Removes a subtask, completing its todo under `auto_complete` when the rest are all done.
+    pub fn remove_subtask(
+        &mut self,
+        id: &Uuid,
+        subtask_id: &Uuid,
+    ) -> Result<UpdateOutcome, TodoRepoError> {
+        let auto_complete = self.auto_complete;
+        let todo = self.get_mut(id)?;
+        let index = todo
+            .subtasks
+            .iter()
+            .position(|subtask| subtask.id == *subtask_id)
+            .ok_or(TodoRepoError::NotFound)?;
+
+        todo.subtasks.remove(index);
+        todo.touch();
+
+        let outcome = UpdateOutcome {
+            completion_changed: auto_complete && follow_subtasks(todo),
+            todo: todo.clone(),
+        };
+        self.version += 1;
+        self.recount();
+
+        Ok(outcome)
+    }
+
+    /// Moves the todo `id` to `new_position` in the manual order, shifting the todos in
+    /// between by one. Positions past the end move it last.
+    ///
+    /// Positions are renumbered from zero, so gaps left by deletions close up.
+    pub fn reorder(&mut self, id: &Uuid, new_position: u32) -> Result<Todo, TodoRepoError> {
+        // Trashed todos keep their stale positions until restored, and cannot be moved.
+        let mut order = self.sorted_items(&TodoListFilter::All, TodoSort::Manual);
+        let index = order
+            .iter()
+            .position(|todo| todo.id == *id)
+            .ok_or(TodoRepoError::NotFound)?;
+        let moved = order.remove(index);
+        order.insert((new_position as usize).min(order.len()), moved);
+
+        let mut is_changed = false;
+
+        for (position, todo) in order.iter().enumerate() {
+            let stored = self
+                .items
+                .get_mut(&todo.id)
+                .ok_or(TodoRepoError::NotFound)?;
+
+            if stored.position != position as u32 {
+                stored.position = position as u32;
+                stored.touch();
+                is_changed = true;
+            }
+        }
+
+        if is_changed {
+            self.version += 1;
+        }
+
+        self.get(id)
+    }
+
+    /// The position right after the last todo in the manual order.
+    fn next_position(&self) -> u32 {
+        self.items
+            .values()
+            .map(|todo| todo.position + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Recomputes every counter from the stored items in a single pass, leaving out the trash.
+    fn recount(&mut self) {
+        let (mut num_completed_items, mut num_in_progress_items, mut num_all_items) = (0, 0, 0);
+
+        for todo in self.items.values().filter(|todo| todo.deleted_at.is_none()) {
+            match todo.status {
+                TodoStatus::Done => num_completed_items += 1,
+                TodoStatus::InProgress => num_in_progress_items += 1,
+                TodoStatus::Todo => {}
+            }
+            num_all_items += 1;
+        }
+
+        self.num_completed_items = num_completed_items;
+        self.num_in_progress_items = num_in_progress_items;
+        self.num_active_items = num_all_items - num_completed_items;
+        self.num_all_items = num_all_items;
+    }
+}
+
+/// Fails with `BlankText` or `TextTooLong` unless `text` passes `models::is_valid_text`.
+fn check_text(text: &str) -> Result<(), TodoRepoError> {
+    if text.trim().is_empty() {
+        Err(TodoRepoError::BlankText)
+    } else if !is_valid_text(text) {
+        Err(TodoRepoError::TextTooLong)
+    } else {
+        Ok(())
+    }
+}
+
+/// Completes `todo` when it has subtasks and all are done, or reopens it when one is open,
+/// returning whether its completion changed.
+fn follow_subtasks(todo: &mut Todo) -> bool {
+    if todo.subtasks.is_empty() {
+        return false;
+    }
+
+    let all_done = todo.subtasks.iter().all(|subtask| subtask.done);
+    let changed = all_done != todo.is_completed();
+
+    if changed {
+        todo.set_completed(all_done);
+    }
+
+    changed
+}
+
+fn sort_newest_first(todos: &mut [Todo]) {
+    todos.sort_by_key(|todo| Reverse((todo.created_at, todo.seq)));
+}
+
+fn sort_oldest_first(todos: &mut [Todo]) {
+    todos.sort_by_key(|todo| (todo.created_at, todo.seq));
+}
+
+fn sort_manual(todos: &mut [Todo]) {
+    todos.sort_by_key(|todo| (todo.position, todo.seq));
+}
+
+fn sort_completed_last(todos: &mut [Todo]) {
+    todos.sort_by_key(|todo| (todo.is_completed(), Reverse((todo.created_at, todo.seq))));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Recurrence;
+
+    #[test]
+    fn test_get_non_existing_todo() {
+        // Arrange
+        let repo = TodoRepo::default();
+        let id = Uuid::new_v4();
+
+        // Act
+        let result = repo.get(&id);
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::NotFound));
+    }
+
+    #[test]
+    fn test_get_by_short() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        repo.create("a").unwrap();
+        let todo = repo.create("b").unwrap();
+
+        // Act
+        let result = repo.get_by_short(&todo.short_id());
+
+        // Assert
+        assert_eq!(todo.short_id(), "1");
+        assert_eq!(result, Ok(todo));
+        assert_eq!(repo.get_by_short("2"), Err(TodoRepoError::NotFound));
+        assert_eq!(repo.get_by_short("not-short"), Err(TodoRepoError::NotFound));
+    }
+
+    #[test]
+    fn test_get_existing_todo() {
+        // Arrange
+        let todo = Todo::new("test");
+        let id = Uuid::new_v4();
+
+        let repo = TodoRepo {
+            items: HashMap::from([(id, todo.clone())]),
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.get(&id);
+
+        // Assert
+        assert_eq!(result, Ok(todo));
+    }
+
+    #[test]
+    fn test_list_empty_repo() {
+        // Arrange
+        let repo = TodoRepo::default();
+        let empty = Vec::new();
+
+        // Act
+        let result_completed = repo.list(&TodoListFilter::Completed);
+        let result_active = repo.list(&TodoListFilter::Active);
+        let result_all = repo.list(&TodoListFilter::All);
+
+        // Assert
+        assert_eq!(result_completed, empty);
+        assert_eq!(result_active, empty);
+        assert_eq!(result_all, empty);
+    }
+
+    #[test]
+    fn test_list_filled_repo_active() {
+        // Arrange
+        let todo_a = Todo::new("a");
+        let todo_b = Todo::new("b");
+        let todo_c = Todo::new("c");
+
+        let filled = vec![todo_c.clone(), todo_b.clone(), todo_a.clone()];
+        let empty = Vec::new();
+
+        let repo = TodoRepo {
+            items: HashMap::from([
+                (Uuid::new_v4(), todo_a),
+                (Uuid::new_v4(), todo_b),
+                (Uuid::new_v4(), todo_c),
+            ]),
+            ..Default::default()
+        };
+
+        // Act
+        let result_completed = repo.list(&TodoListFilter::Completed);
+        let result_active = repo.list(&TodoListFilter::Active);
+        let result_all = repo.list(&TodoListFilter::All);
+
+        // Assert
+        assert_eq!(result_completed, empty);
+        assert_eq!(result_active, filled);
+        assert_eq!(result_all, filled);
+    }
+
+    #[test]
+    fn test_list_filled_repo_completed() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        let mut todo_b = Todo::new("b");
+        let todo_c = Todo::new("c");
+
+        todo_a.status = TodoStatus::Done;
+        todo_b.status = TodoStatus::Done;
+
+        let completed = vec![todo_b.clone(), todo_a.clone()];
+        let active = vec![todo_c.clone()];
+        let all = vec![todo_c.clone(), todo_b.clone(), todo_a.clone()];
+
+        let repo = TodoRepo {
+            items: HashMap::from([
+                (Uuid::new_v4(), todo_a),
+                (Uuid::new_v4(), todo_b),
+                (Uuid::new_v4(), todo_c),
+            ]),
+            ..Default::default()
+        };
+
+        // Act
+        let result_completed = repo.list(&TodoListFilter::Completed);
+        let result_active = repo.list(&TodoListFilter::Active);
+        let result_all = repo.list(&TodoListFilter::All);
+
+        // Assert
+        assert_eq!(result_completed, completed);
+        assert_eq!(result_active, active);
+        assert_eq!(result_all, all);
+    }
+
+    #[test]
+    fn test_query_by_tag() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        let mut todo_b = Todo::new("b");
+        let todo_c = Todo::new("c");
+
+        todo_a.tags = vec![String::from("work")];
+        todo_b.tags = vec![String::from("work"), String::from("home")];
+
+        let repo = TodoRepo {
+            items: HashMap::from([
+                (Uuid::new_v4(), todo_a.clone()),
+                (Uuid::new_v4(), todo_b.clone()),
+                (Uuid::new_v4(), todo_c),
+            ]),
+            ..Default::default()
+        };
+
+        let by_tag = |tag: &str| {
+            repo.query(&QueryParams {
+                tag: Some(String::from(tag)),
+                ..Default::default()
+            })
+            .items
+        };
+
+        // Act
+        let result_work = by_tag(" WORK ");
+        let result_home = by_tag("home");
+        let result_partial = by_tag("wor");
+        let result_empty = by_tag(" ");
+
+        // Assert
+        assert_eq!(result_work, vec![todo_b.clone(), todo_a]);
+        assert_eq!(result_home, vec![todo_b]);
+        assert!(result_partial.is_empty());
+        assert!(result_empty.is_empty());
+    }
+
+    #[test]
+    fn test_create_todo() {
+        // Arrange
+        let mut repo = TodoRepo {
+            items: HashMap::from([(Uuid::new_v4(), Todo::new("a"))]),
+            num_completed_items: 0,
+            num_active_items: 1,
+            num_all_items: 1,
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.create("new").unwrap();
+
+        // Assert
+        assert_eq!(result.text, "new".to_string());
+        assert!(!result.is_completed());
+
+        assert_eq!(repo.num_completed_items, 0);
+        assert_eq!(repo.num_active_items, 2);
+        assert_eq!(repo.num_all_items, 2);
+    }
+
+    #[test]
+    fn test_create_at_capacity() {
+        // Arrange
+        let mut repo = TodoRepo::with_capacity(2);
+
+        // Act
+        let first = repo.create("a");
+        let second = repo.create("b");
+        let third = repo.create("c");
+
+        // Assert
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        assert_eq!(third, Err(TodoRepoError::CapacityReached));
+        assert_eq!(repo.num_all_items, 2);
+    }
+
+    #[test]
+    fn test_create_after_delete_at_capacity() {
+        // Arrange
+        let mut repo = TodoRepo::with_capacity(1);
+        let todo = repo.create("a").unwrap();
+
+        repo.delete(&todo.id).unwrap();
+
+        // Act
+        let result = repo.create("b");
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    #[test]
+    fn test_import_above_capacity() {
+        // Arrange
+        let mut repo = TodoRepo::with_capacity(2);
+
+        // Act
+        let result = repo.import(vec![Todo::new("a"), Todo::new("b"), Todo::new("c")]);
+
+        // Assert
+        assert_eq!(result, (2, 1));
+        assert_eq!(repo.num_all_items, 2);
+    }
+
+    #[test]
+    fn test_list_preserves_insertion_order() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let mut created = (0..10)
+            .map(|i| repo.create(&i.to_string()).unwrap())
+            .collect::<Vec<_>>();
+
+        created.reverse();
+
+        // Act
+        let result = repo.list(&TodoListFilter::All);
+
+        // Assert
+        assert_eq!(result, created);
+    }
+
+    #[test]
+    fn test_list_orders_shared_timestamps_by_seq() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let created_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        for i in 0..50 {
+            let id = repo.create(&i.to_string()).unwrap().id;
+            repo.items.get_mut(&id).unwrap().created_at = created_at;
+        }
+
+        let texts = |repo: &TodoRepo| {
+            repo.list(&TodoListFilter::All)
+                .into_iter()
+                .map(|todo| todo.text)
+                .collect::<Vec<_>>()
+        };
+
+        // Act
+        let first = texts(&repo);
+        let second = texts(&repo.clone());
+
+        // Assert
+        let expected = (0..50).rev().map(|i| i.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(first, expected);
+        assert_eq!(second, expected);
+    }
+
+    #[test]
+    fn test_list_with_sort_oldest() {
+        // Arrange
+        let mut repo = TodoRepo::with_sort(TodoSort::Oldest);
+
+        for text in ["a", "b", "c"] {
+            repo.create(text).unwrap();
+        }
+
+        // Act
+        let texts = repo
+            .list(&TodoListFilter::All)
+            .into_iter()
+            .map(|todo| todo.text)
+            .collect::<Vec<_>>();
+
+        // Assert
+        assert_eq!(texts, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_import_todos() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let existing = repo.create("existing").unwrap();
+
+        let mut completed = Todo::new("completed");
+        completed.status = TodoStatus::Done;
+
+        let new = Todo::new("new");
+        let blank = Todo::new("  ");
+        let too_long = Todo::new(&"a".repeat(MAX_TEXT_LEN + 1));
+
+        // Act
+        let result = repo.import(vec![
+            existing.clone(),
+            new.clone(),
+            completed.clone(),
+            blank,
+            too_long,
+        ]);
+
+        // Assert
+        assert_eq!(result, (2, 3));
+
+        assert_eq!(repo.get(&existing.id).unwrap().text, "existing");
+        assert_eq!(repo.get(&new.id).unwrap().created_at, new.created_at);
+        assert!(repo.get(&completed.id).unwrap().is_completed());
+        assert_eq!(repo.get(&new.id).unwrap().source, TodoSource::Import);
+        assert_eq!(repo.get(&existing.id).unwrap().source, TodoSource::Web);
+
+        assert_eq!(repo.counters(), (1, 2, 3));
+    }
+
+    #[test]
+    fn test_delete_non_existing_todo() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = Uuid::new_v4();
+
+        // Act
+        let result = repo.delete(&id);
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::NotFound));
+    }
+
+    #[test]
+    fn test_delete_existing_todo() {
+        // Arrange
+        let id = Uuid::new_v4();
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(id, Todo::new("a")), (Uuid::new_v4(), Todo::new("b"))]),
+            num_completed_items: 0,
+            num_active_items: 2,
+            num_all_items: 2,
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.delete(&id);
+
+        // Assert
+        assert_eq!(repo.num_completed_items, 0);
+        assert_eq!(repo.num_active_items, 1);
+        assert_eq!(repo.num_all_items, 1);
+
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_update_non_existing_todo() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = Uuid::new_v4();
+
+        // Act
+        let result = repo.update(&id, TodoUpdate::default());
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::NotFound));
+    }
+
+    #[test]
+    fn test_update_text_existing_todo() {
+        // Arrange
+        let todo = Todo::new("test");
+        let id = Uuid::new_v4();
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(id, todo.clone())]),
+            num_completed_items: 0,
+            num_active_items: 1,
+            num_all_items: 1,
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.update(
+            &id,
+            TodoUpdate {
+                text: Some("update".to_string()),
+                ..Default::default()
+            },
+        );
+
+        // Assert
+        assert!(result.is_ok());
+
+        if let Ok(UpdateOutcome {
+            todo: update,
+            completion_changed,
+        }) = result
+        {
+            assert!(!completion_changed);
+            assert_eq!(update.is_completed(), todo.is_completed());
+            assert_eq!(update.created_at, todo.created_at);
+            assert_eq!(update.text, "update".to_string());
+            assert_eq!(update.id, todo.id);
+        }
+
+        assert_eq!(repo.num_completed_items, 0);
+        assert_eq!(repo.num_active_items, 1);
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    #[test]
+    fn test_update_is_completed_true_existing_todo() {
+        // Arrange
+        let todo = Todo::new("test");
+        let id = Uuid::new_v4();
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(id, todo.clone())]),
+            num_completed_items: 0,
+            num_active_items: 1,
+            num_all_items: 1,
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.update(
+            &id,
+            TodoUpdate {
+                is_completed: Some(true),
+                ..Default::default()
+            },
+        );
+
+        // Assert
+        assert!(result.is_ok());
+
+        if let Ok(UpdateOutcome {
+            todo: update,
+            completion_changed,
+        }) = result
+        {
+            assert!(completion_changed);
+            assert_eq!(update.created_at, todo.created_at);
+            assert_eq!(update.text, todo.text);
+            assert_eq!(update.id, todo.id);
+            assert!(update.is_completed());
+        }
+
+        assert_eq!(repo.num_completed_items, 1);
+        assert_eq!(repo.num_active_items, 0);
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    #[test]
+    fn test_update_is_completed_false_existing_todo() {
+        // Arrange
+        let mut todo = Todo::new("test");
+        let id = Uuid::new_v4();
+
+        todo.status = TodoStatus::Done;
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(id, todo.clone())]),
+            num_completed_items: 1,
+            num_active_items: 0,
+            num_all_items: 1,
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.update(
+            &id,
+            TodoUpdate {
+                is_completed: Some(false),
+                ..Default::default()
+            },
+        );
+
+        // Assert
+        assert!(result.is_ok());
+
+        if let Ok(UpdateOutcome {
+            todo: update,
+            completion_changed,
+        }) = result
+        {
+            assert!(completion_changed);
+            assert_eq!(update.created_at, todo.created_at);
+            assert_eq!(update.text, todo.text);
+            assert_eq!(update.id, todo.id);
+            assert!(!update.is_completed());
+        }
+
+        assert_eq!(repo.num_completed_items, 0);
+        assert_eq!(repo.num_active_items, 1);
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    #[test]
+    fn test_update_is_completed_unchanged_existing_todo() {
+        // Arrange
+        let mut todo = Todo::new("test");
+        let id = Uuid::new_v4();
+
+        todo.status = TodoStatus::Done;
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(id, todo)]),
+            num_completed_items: 1,
+            num_active_items: 0,
+            num_all_items: 1,
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.update(
+            &id,
+            TodoUpdate {
+                is_completed: Some(true),
+                ..Default::default()
+            },
+        );
+
+        // Assert
+        assert!(result.is_ok_and(|outcome| !outcome.completion_changed));
+
+        assert_eq!(repo.num_completed_items, 1);
+        assert_eq!(repo.num_active_items, 0);
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    #[test]
+    fn test_update_status_transitions() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("a").unwrap().id;
+        let status = |status| TodoUpdate {
+            status: Some(status),
+            ..Default::default()
+        };
+        let is_completed = |is_completed| TodoUpdate {
+            is_completed: Some(is_completed),
+            ..Default::default()
+        };
+
+        // Act
+        let started = repo.update(&id, status(TodoStatus::InProgress)).unwrap();
+        let counters_started = (repo.num_in_progress_items, repo.counters());
+        let unchecked = repo.update(&id, is_completed(false)).unwrap();
+        let done = repo.update(&id, is_completed(true)).unwrap();
+        let counters_done = (repo.num_in_progress_items, repo.counters());
+        let restarted = repo.update(&id, status(TodoStatus::InProgress)).unwrap();
+        let reopened = repo.update(&id, is_completed(false)).unwrap();
+
+        // Assert
+        assert_eq!(started.todo.status, TodoStatus::InProgress);
+        assert!(!started.completion_changed);
+        assert_eq!(started.todo.completed_at, None);
+        assert_eq!(counters_started, (1, (0, 1, 1)));
+
+        // Unchecking an open todo leaves it started.
+        assert_eq!(unchecked.todo.status, TodoStatus::InProgress);
+        assert_eq!(unchecked.todo.revision, started.todo.revision);
+
+        assert_eq!(done.todo.status, TodoStatus::Done);
+        assert!(done.completion_changed);
+        assert!(done.todo.completed_at.is_some());
+        assert_eq!(counters_done, (0, (1, 0, 1)));
+
+        assert_eq!(restarted.todo.status, TodoStatus::InProgress);
+        assert!(restarted.completion_changed);
+        assert_eq!(restarted.todo.completed_at, None);
+
+        assert_eq!(reopened.todo.status, TodoStatus::InProgress);
+    }
+
+    #[test]
+    fn test_toggle_completed_keeps_in_progress_open() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let started = repo.create("started").unwrap().id;
+        let done = repo.create("done").unwrap().id;
+        repo.update(
+            &started,
+            TodoUpdate {
+                status: Some(TodoStatus::InProgress),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        repo.set_completed(&done, true).unwrap();
+
+        // Act
+        repo.toggle_completed(&TodoToggleAction::Uncheck, &TodoListFilter::All);
+        let unchecked = (
+            repo.get(&started).unwrap().status,
+            repo.get(&done).unwrap().status,
+        );
+
+        repo.toggle_completed(&TodoToggleAction::Check, &TodoListFilter::InProgress);
+        let checked = (
+            repo.get(&started).unwrap().status,
+            repo.get(&done).unwrap().status,
+        );
+
+        // Assert
+        assert_eq!(unchecked, (TodoStatus::InProgress, TodoStatus::Todo));
+        assert_eq!(checked, (TodoStatus::Done, TodoStatus::Todo));
+        assert_eq!(repo.num_in_progress_items, 0);
+        assert_eq!(repo.counters(), (1, 1, 2));
+    }
+
+    #[test]
+    fn test_by_quadrant() {
+        // Arrange
+        let mut todo_do = Todo::new("do");
+        let mut todo_schedule = Todo::new("schedule");
+        let mut todo_delegate = Todo::new("delegate");
+        let todo_eliminate = Todo::new("eliminate");
+
+        todo_do.is_urgent = true;
+        todo_do.is_important = true;
+        todo_schedule.is_important = true;
+        todo_delegate.is_urgent = true;
+
+        let repo = TodoRepo {
+            items: HashMap::from([
+                (Uuid::new_v4(), todo_do.clone()),
+                (Uuid::new_v4(), todo_schedule.clone()),
+                (Uuid::new_v4(), todo_delegate.clone()),
+                (Uuid::new_v4(), todo_eliminate.clone()),
+            ]),
+            ..Default::default()
+        };
+
+        // Act
+        let result = repo.by_quadrant();
+
+        // Assert
+        assert_eq!(
+            result,
+            [
+                vec![todo_do],
+                vec![todo_schedule],
+                vec![todo_delegate],
+                vec![todo_eliminate]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_velocity() {
+        // Arrange
+        let now = SystemTime::now();
+        let minute = Duration::from_secs(60);
+
+        let mut items = HashMap::new();
+
+        for minutes_ago in [10, 30, 50, 90, 150] {
+            let mut todo = Todo::new("done");
+
+            todo.status = TodoStatus::Done;
+            todo.completed_at = Some(now - minute * minutes_ago);
+            items.insert(todo.id, todo);
+        }
+
+        let active = Todo::new("active");
+        items.insert(active.id, active);
+
+        let repo = TodoRepo {
+            items,
+            ..Default::default()
+        };
+
+        // Act
+        let result_one_hour = repo.velocity(minute * 60, now);
+        let result_two_hours = repo.velocity(minute * 120, now);
+        let result_empty = repo.velocity(Duration::ZERO, now);
+
+        // Assert
+        assert_eq!(result_one_hour, 3.0);
+        assert_eq!(result_two_hours, 2.0);
+        assert_eq!(result_empty, 0.0);
+    }
+
+    #[test]
+    fn test_recount_corrupted_counters() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        let todo_b = Todo::new("b");
+        let todo_c = Todo::new("c");
+
+        todo_a.status = TodoStatus::Done;
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([
+                (Uuid::new_v4(), todo_a),
+                (Uuid::new_v4(), todo_b),
+                (Uuid::new_v4(), todo_c),
+            ]),
+            num_completed_items: 7,
+            num_active_items: 0,
+            num_all_items: 1,
+            ..Default::default()
+        };
+
+        // Act
+        repo.recount();
+
+        // Assert
+        assert_eq!(repo.counters(), (1, 2, 3));
+    }
+
+    #[test]
+    fn test_update_blank_description_clears_it() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+
+        repo.update(
+            &id,
+            TodoUpdate {
+                description: Some("details".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Act
+        let result = repo
+            .update(
+                &id,
+                TodoUpdate {
+                    description: Some("  ".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // Assert
+        assert_eq!(result.todo.description, None);
+        assert_eq!(result.todo.text, "test");
+    }
+
+    #[test]
+    fn test_update_bumps_revision_on_change() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+
+        // Act
+        let changed = repo
+            .update(
+                &id,
+                TodoUpdate {
+                    text: Some("update".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let unchanged = repo
+            .update(
+                &id,
+                TodoUpdate {
+                    text: Some("update".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // Assert
+        assert_eq!(changed.todo.revision, 1);
+        assert_eq!(unchanged.todo.revision, 1);
+    }
+
+    #[test]
+    fn test_set_completed_twice() {
+        // Arrange
+        let todo = Todo::new("test");
+        let id = Uuid::new_v4();
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(id, todo), (Uuid::new_v4(), Todo::new("other"))]),
+            num_completed_items: 0,
+            num_active_items: 2,
+            num_all_items: 2,
+            ..Default::default()
+        };
+
+        // Act
+        let first = repo.set_completed(&id, true).unwrap();
+        let second = repo.set_completed(&id, true).unwrap();
+
+        // Assert
+        assert!(first.todo.is_completed());
+        assert!(first.completion_changed);
+        assert!(!second.completion_changed);
+        assert_eq!(first.todo, second.todo);
+
+        assert_eq!(repo.counters(), (1, 1, 2));
+    }
+
+    #[test]
+    fn test_set_completed_non_existing_todo() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+
+        // Act
+        let result = repo.set_completed(&Uuid::new_v4(), true);
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::NotFound));
+    }
+
+    #[test]
+    fn test_create_unique_duplicate() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        repo.create("test").unwrap();
+
+        // Act
+        let result = repo.create_unique("  test ");
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::Duplicate));
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    #[test]
+    fn test_create_unique_normalized_duplicate() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        repo.create("a b").unwrap();
+
+        // Act
+        let result = repo.create_unique("a  \t b");
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::Duplicate));
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    #[test]
+    fn test_create_unique_completed_duplicate() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("test").unwrap();
+        repo.set_completed(&todo.id, true).unwrap();
+
+        // Act
+        let result = repo.create_unique("test");
+
+        // Assert
+        assert!(result.is_ok());
+        assert_eq!(repo.num_all_items, 2);
+    }
+
+    #[test]
+    fn test_create_allows_duplicates() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        repo.create("test").unwrap();
+
+        // Act
+        let todo = repo.create("test").unwrap();
+
+        // Assert
+        assert_eq!(todo.text, "test");
+        assert_eq!(repo.num_all_items, 2);
+    }
+
+    #[test]
+    fn test_summary_line_empty() {
+        // Arrange
+        let repo = TodoRepo::default();
+
+        // Act
+        let result = repo.summary_line();
+
+        // Assert
+        assert_eq!(result, "no todos");
+    }
+
+    #[test]
+    fn test_summary_line_singular() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        repo.create("a").unwrap();
+
+        // Act
+        let result = repo.summary_line();
+
+        // Assert
+        assert_eq!(result, "1 active, 0 done (1 total)");
+    }
+
+    #[test]
+    fn test_summary_line_plural() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
+
+        repo.create("b").unwrap();
+        repo.create("c").unwrap();
+        repo.set_completed(&todo.id, true).unwrap();
+
+        // Act
+        let result = repo.summary_line();
+
+        // Assert
+        assert_eq!(result, "2 active, 1 done (3 total)");
+    }
+
+    #[test]
+    fn test_add_subtask() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+
+        // Act
+        let result = repo.add_subtask(&id, "step").unwrap();
+
+        // Assert
+        assert_eq!(result.todo.subtasks.len(), 1);
+        assert_eq!(result.todo.subtasks[0].text, "step");
+        assert!(!result.todo.subtasks[0].done);
+        assert_eq!(repo.get(&id).unwrap(), result.todo);
+        assert_eq!(repo.counters(), (0, 1, 1));
+    }
+
+    #[test]
+    fn test_add_subtask_non_existing_todo() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+
+        // Act
+        let result = repo.add_subtask(&Uuid::new_v4(), "step");
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::NotFound));
+    }
+
+    #[test]
+    fn test_toggle_subtask() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+        let first = repo.add_subtask(&id, "first").unwrap().todo.subtasks[0].id;
+        let second = repo.add_subtask(&id, "second").unwrap().todo.subtasks[1].id;
+
+        // Act
+        let partial = repo.toggle_subtask(&id, &first).unwrap();
+        let done = repo.toggle_subtask(&id, &second).unwrap();
+
+        // Assert
+        assert!(partial.todo.subtasks[0].done);
+        assert!(!partial.todo.is_completed());
+        assert!(!partial.completion_changed);
+
+        assert!(done.todo.is_completed());
+        assert!(done.completion_changed);
+        assert_eq!(repo.counters(), (1, 0, 1));
+    }
+
+    #[test]
+    fn test_toggle_subtask_non_existing_subtask() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+
+        // Act
+        let result = repo.toggle_subtask(&id, &Uuid::new_v4());
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::NotFound));
+    }
+
+    #[test]
+    fn test_remove_subtask() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+        let subtask_id = repo.add_subtask(&id, "step").unwrap().todo.subtasks[0].id;
+
+        // Act
+        let result = repo.remove_subtask(&id, &subtask_id).unwrap();
+
+        // Assert
+        assert!(result.todo.subtasks.is_empty());
+    }
+
+    #[test]
+    fn test_subtasks_reopen_and_complete_todo() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+        let first = repo.add_subtask(&id, "first").unwrap().todo.subtasks[0].id;
+        let second = repo.add_subtask(&id, "second").unwrap().todo.subtasks[1].id;
+        repo.toggle_subtask(&id, &first).unwrap();
+        repo.toggle_subtask(&id, &second).unwrap();
+
+        // Act
+        let unchecked = repo.toggle_subtask(&id, &second).unwrap();
+        let removed = repo.remove_subtask(&id, &second).unwrap();
+
+        // Assert
+        assert!(!unchecked.todo.is_completed());
+        assert!(unchecked.completion_changed);
+        assert!(removed.todo.is_completed());
+        assert!(removed.completion_changed);
+        assert_eq!(repo.counters(), (1, 0, 1));
+    }
+
+    #[test]
+    fn test_subtasks_without_auto_complete() {
+        // Arrange
+        let mut repo = TodoRepo {
+            auto_complete: false,
+            ..Default::default()
+        };
+        let id = repo.create("test").unwrap().id;
+        let subtask_id = repo.add_subtask(&id, "step").unwrap().todo.subtasks[0].id;
+
+        // Act
+        let result = repo.toggle_subtask(&id, &subtask_id).unwrap();
+
+        // Assert
+        assert!(result.todo.subtasks[0].done);
+        assert!(!result.todo.is_completed());
+        assert!(!result.completion_changed);
+    }
+
+    #[test]
+    fn test_add_subtask_invalid_text() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+
+        // Act
+        let blank = repo.add_subtask(&id, "  ");
+        let too_long = repo.add_subtask(&id, &"a".repeat(MAX_TEXT_LEN + 1));
+
+        // Assert
+        assert_eq!(blank, Err(TodoRepoError::BlankText));
+        assert_eq!(too_long, Err(TodoRepoError::TextTooLong));
+        assert!(repo.get(&id).unwrap().subtasks.is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        todo_a.status = TodoStatus::Done;
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(Uuid::new_v4(), todo_a), (Uuid::new_v4(), Todo::new("b"))]),
+            num_completed_items: 1,
+            num_active_items: 1,
+            num_all_items: 2,
+            ..Default::default()
+        };
+
+        // Act
+        repo.clear();
+
+        // Assert
+        assert!(repo.items.is_empty());
+
+        assert_eq!(repo.num_completed_items, 0);
+        assert_eq!(repo.num_active_items, 0);
+        assert_eq!(repo.num_all_items, 0);
+    }
+
+    #[test]
+    fn test_delete_completed_todos() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        let mut todo_b = Todo::new("b");
+        let todo_c = Todo::new("c");
+
+        todo_a.status = TodoStatus::Done;
+        todo_b.status = TodoStatus::Done;
+
+        let active = vec![todo_c.clone()];
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([
+                (Uuid::new_v4(), todo_a),
+                (Uuid::new_v4(), todo_b),
+                (Uuid::new_v4(), todo_c),
+            ]),
+            num_completed_items: 2,
+            num_active_items: 1,
+            num_all_items: 3,
+            ..Default::default()
+        };
+
+        // Act
+        repo.delete_completed();
+
+        // Assert
+        assert_eq!(repo.items.into_values().collect::<Vec<_>>(), active);
+
+        assert_eq!(repo.num_completed_items, 0);
+        assert_eq!(repo.num_active_items, 1);
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    fn duplicated_repo() -> (TodoRepo, Uuid, Uuid) {
+        let mut repo = TodoRepo::default();
+
+        let oldest = repo.create("Buy milk").unwrap();
+        repo.create("  buy   MILK ").unwrap();
+        let completed = repo.create("buy milk").unwrap();
+        repo.create("walk the dog").unwrap();
+        repo.create("Walk the dog").unwrap();
+
+        repo.set_completed(&completed.id, true).unwrap();
+
+        (repo, oldest.id, completed.id)
+    }
+
+    #[test]
+    fn test_deduplicate_keep_oldest() {
+        // Arrange
+        let (mut repo, oldest, completed) = duplicated_repo();
+
+        // Act
+        let result = repo.deduplicate(TodoDedupKeep::Oldest);
+
+        // Assert
+        assert_eq!(result, 3);
+
+        assert!(repo.get(&oldest).is_ok());
+        assert_eq!(repo.get(&completed), Err(TodoRepoError::NotFound));
+
+        assert_eq!(repo.counters(), (0, 2, 2));
+    }
+
+    #[test]
+    fn test_deduplicate_keep_completed() {
+        // Arrange
+        let (mut repo, oldest, completed) = duplicated_repo();
+
+        // Act
+        let result = repo.deduplicate(TodoDedupKeep::Completed);
+
+        // Assert
+        assert_eq!(result, 3);
+
+        assert_eq!(repo.get(&oldest), Err(TodoRepoError::NotFound));
+        assert!(repo.get(&completed).is_ok());
+
+        assert_eq!(repo.counters(), (1, 1, 2));
+    }
+
+    #[test]
+    fn test_deduplicate_without_duplicates() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+
+        repo.create("a").unwrap();
+        repo.create("b").unwrap();
+
+        // Act
+        let result = repo.deduplicate(TodoDedupKeep::Oldest);
+
+        // Assert
+        assert_eq!(result, 0);
+        assert_eq!(repo.counters(), (0, 2, 2));
+    }
+
+    #[test]
+    fn test_update_completed_at() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+
+        // Act
+        let completed = repo.set_completed(&id, true).unwrap().todo;
+        let uncompleted = repo.set_completed(&id, false).unwrap().todo;
+
+        // Assert
+        assert!(completed.completed_at.is_some());
+        assert_eq!(uncompleted.completed_at, None);
+    }
+
+    #[test]
+    fn test_toggle_completed_at() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let id = repo.create("test").unwrap().id;
+
+        // Act
+        repo.toggle_completed(&TodoToggleAction::Check, &TodoListFilter::All);
+        let completed = repo.get(&id).unwrap();
+
+        repo.toggle_completed(&TodoToggleAction::Uncheck, &TodoListFilter::All);
+        let uncompleted = repo.get(&id).unwrap();
+
+        // Assert
+        assert!(completed.completed_at.is_some());
+        assert_eq!(uncompleted.completed_at, None);
+    }
+
+    #[test]
+    fn test_set_completed_many() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let a = repo.create("a").unwrap().id;
+        let b = repo.create("b").unwrap().id;
+        let c = repo.create("c").unwrap().id;
+        let d = repo.create("d").unwrap().id;
+        repo.set_completed(&d, true).unwrap();
+
+        // Act
+        let changed = repo.set_completed_many(&[a, b, b, d, Uuid::new_v4()], true);
+        let changed_again = repo.set_completed_many(&[a, b], true);
+
+        // Assert
+        assert_eq!(changed, vec![a, b]);
+        assert!(changed_again.is_empty());
+
+        assert!(repo.get(&a).unwrap().is_completed());
+        assert!(repo.get(&b).unwrap().is_completed());
+        assert!(!repo.get(&c).unwrap().is_completed());
+        assert_eq!(repo.get(&c).unwrap().revision, 0);
+
+        assert_eq!(repo.num_completed_items, 3);
+        assert_eq!(repo.num_active_items, 1);
+        assert_eq!(repo.num_all_items, 4);
+    }
+
+    #[test]
+    fn test_add_and_remove_tag_many() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let a = repo.create("a").unwrap().id;
+        let b = repo.create("b").unwrap().id;
+        let c = repo.create("c").unwrap().id;
+        let tags = |repo: &TodoRepo, id| repo.get(&id).unwrap().tags;
+
+        // Act
+        let added = repo.add_tag_many(&[a, b, Uuid::new_v4()], " Work ");
+        let added_again = repo.add_tag_many(&[a], "work").unwrap();
+        let blank = repo.add_tag_many(&[c], "  ").unwrap();
+        let several = repo.add_tag_many(&[c], " a,b ");
+
+        // Assert
+        assert_eq!(added, Ok(vec![a, b]));
+        assert!(added_again.is_empty());
+        assert!(blank.is_empty());
+        assert_eq!(several, Err(TodoRepoError::InvalidTag));
+        assert_eq!(tags(&repo, a), ["work"]);
+        assert_eq!(tags(&repo, b), ["work"]);
+        assert!(tags(&repo, c).is_empty());
+
+        // Act
+        let removed = repo.remove_tag_many(&[a, c], "WORK");
+        let removed_several = repo.remove_tag_many(&[b], "work,x");
+
+        // Assert
+        assert_eq!(removed, Ok(vec![a]));
+        assert_eq!(removed_several, Err(TodoRepoError::InvalidTag));
+        assert!(tags(&repo, a).is_empty());
+        assert_eq!(tags(&repo, b), ["work"]);
+    }
+
+    #[test]
+    fn test_purge_completed_older_than() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(30 * 24 * 60 * 60);
+        let age = Duration::from_secs(7 * 24 * 60 * 60);
+        let todos = [
+            ("old", Some(age + Duration::from_secs(1))),
+            ("exactly the age", Some(age)),
+            ("fresh", Some(Duration::from_secs(60))),
+            ("open", None),
+        ];
+
+        for (text, completed_for) in todos {
+            let id = repo.create(text).unwrap().id;
+
+            if let Some(completed_for) = completed_for {
+                let todo = repo.items.get_mut(&id).unwrap();
+                todo.status = TodoStatus::Done;
+                todo.completed_at = Some(now - completed_for);
+            }
+        }
+
+        let trashed = repo.create("old but trashed").unwrap().id;
+        let todo = repo.items.get_mut(&trashed).unwrap();
+        todo.status = TodoStatus::Done;
+        todo.completed_at = Some(now - 2 * age);
+        repo.delete(&trashed).unwrap();
+        repo.recount();
+
+        // Act
+        let removed = repo.purge_completed_older_than(age, now);
+        let removed_again = repo.purge_completed_older_than(age, now);
+
+        // Assert
+        assert_eq!(removed, 1);
+        assert_eq!(removed_again, 0);
+        assert_eq!(repo.counters(), (2, 1, 3));
+        assert!(repo
+            .list(&TodoListFilter::Trash)
+            .iter()
+            .any(|todo| todo.id == trashed));
+
+        let mut texts = repo
+            .list(&TodoListFilter::All)
+            .into_iter()
+            .map(|todo| todo.text)
+            .collect::<Vec<_>>();
+        texts.sort();
+
+        assert_eq!(texts, ["exactly the age", "fresh", "open"]);
+    }
+
+    #[test]
+    fn test_uncomplete_all() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let done = repo.create("done").unwrap().id;
+        let started = repo.create("started").unwrap().id;
+        repo.create("open").unwrap();
+        repo.set_completed(&done, true).unwrap();
+        repo.update(
+            &started,
+            TodoUpdate {
+                status: Some(TodoStatus::InProgress),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Act
+        let reopened = repo.uncomplete_all();
+        let reopened_again = repo.uncomplete_all();
+
+        // Assert
+        assert_eq!(reopened, 1);
+        assert_eq!(reopened_again, 0);
+        assert_eq!(repo.num_completed_items, 0);
+        assert_eq!(repo.counters(), (0, 3, 3));
+        assert_eq!(repo.get(&done).unwrap().status, TodoStatus::Todo);
+        assert_eq!(repo.get(&done).unwrap().completed_at, None);
+        assert_eq!(repo.get(&started).unwrap().status, TodoStatus::InProgress);
+    }
+
+    #[test]
+    fn test_toggle_check_completed_todos() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        let mut todo_b = Todo::new("b");
+        let todo_c = Todo::new("c");
+        let id = Uuid::new_v4();
+
+        todo_a.status = TodoStatus::Done;
+        todo_b.status = TodoStatus::Done;
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([
+                (Uuid::new_v4(), todo_a),
+                (Uuid::new_v4(), todo_b),
+                (id, todo_c),
+            ]),
+            num_completed_items: 2,
+            num_active_items: 1,
+            num_all_items: 3,
+            ..Default::default()
+        };
+
+        // Act
+        repo.toggle_completed(&TodoToggleAction::Check, &TodoListFilter::All);
+
+        // Assert
+        assert!(repo.items.get(&id).unwrap().is_completed());
+
+        assert_eq!(repo.num_completed_items, 3);
+        assert_eq!(repo.num_active_items, 0);
+        assert_eq!(repo.num_all_items, 3);
+    }
+
+    #[test]
+    fn test_toggle_uncheck_completed_todos() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        let todo_b = Todo::new("b");
+        let todo_c = Todo::new("c");
+        let id = Uuid::new_v4();
+
+        todo_a.status = TodoStatus::Done;
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([
+                (Uuid::new_v4(), todo_b),
+                (Uuid::new_v4(), todo_c),
+                (id, todo_a),
+            ]),
+            num_completed_items: 1,
+            num_active_items: 2,
+            num_all_items: 3,
+            ..Default::default()
+        };
+
+        // Act
+        repo.toggle_completed(&TodoToggleAction::Uncheck, &TodoListFilter::All);
+
+        // Assert
+        assert!(!repo.items.get(&id).unwrap().is_completed());
+
+        assert_eq!(repo.num_completed_items, 0);
+        assert_eq!(repo.num_active_items, 3);
+        assert_eq!(repo.num_all_items, 3);
+    }
+
+    #[test]
+    fn test_toggle_check_active_filter_todos() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        let todo_b = Todo::new("b");
+        let todo_c = Todo::new("c");
+        let id_a = Uuid::new_v4();
+        let id_b = Uuid::new_v4();
+        let id_c = Uuid::new_v4();
+
+        todo_a.status = TodoStatus::Done;
+        todo_a.completed_at = Some(SystemTime::UNIX_EPOCH);
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(id_a, todo_a), (id_b, todo_b), (id_c, todo_c)]),
+            num_completed_items: 1,
+            num_active_items: 2,
+            num_all_items: 3,
+            ..Default::default()
+        };
+
+        // Act
+        repo.toggle_completed(&TodoToggleAction::Check, &TodoListFilter::Active);
+
+        // Assert
+        assert!(repo.items.get(&id_b).unwrap().is_completed());
+        assert!(repo.items.get(&id_c).unwrap().is_completed());
+        assert_eq!(
+            repo.items.get(&id_a).unwrap().completed_at,
+            Some(SystemTime::UNIX_EPOCH)
+        );
+
+        assert_eq!(repo.num_completed_items, 3);
+        assert_eq!(repo.num_active_items, 0);
+        assert_eq!(repo.num_all_items, 3);
+    }
+
+    #[test]
+    fn test_toggle_uncheck_active_filter_todos() {
+        // Arrange
+        let mut todo_a = Todo::new("a");
+        let todo_b = Todo::new("b");
+        let id = Uuid::new_v4();
+
+        todo_a.status = TodoStatus::Done;
+
+        let mut repo = TodoRepo {
+            items: HashMap::from([(id, todo_a), (Uuid::new_v4(), todo_b)]),
+            num_completed_items: 1,
+            num_active_items: 1,
+            num_all_items: 2,
+            ..Default::default()
+        };
+
+        // Act
+        repo.toggle_completed(&TodoToggleAction::Uncheck, &TodoListFilter::Active);
+
+        // Assert
+        assert!(repo.items.get(&id).unwrap().is_completed());
+
+        assert_eq!(repo.num_completed_items, 1);
+        assert_eq!(repo.num_active_items, 1);
+        assert_eq!(repo.num_all_items, 2);
+    }
+
+    #[test]
+    fn test_list_after_pages_without_gaps() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let created_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        for (i, text) in ["a", "b", "c", "d", "e"].into_iter().enumerate() {
+            let id = repo.create(text).unwrap().id;
+
+            // "b", "c" and "d" share a timestamp, so only their ids tell them apart.
+            repo.items.get_mut(&id).unwrap().created_at =
+                created_at + Duration::from_secs([0, 1, 1, 1, 2][i]);
+        }
+
+        // Act
+        let first = repo.list_after(&TodoListFilter::All, None, 3);
+        let second = repo.list_after(&TodoListFilter::All, first.next, 3);
+
+        // Assert
+        let mut seen = first
+            .items
+            .iter()
+            .chain(&second.items)
+            .map(|todo| todo.text.clone())
+            .collect::<Vec<_>>();
+
+        assert_eq!(first.items.len(), 3);
+        assert_eq!(first.next, Some(TodoCursor::of(&first.items[2])));
+        assert_eq!(second.items.len(), 2);
+        assert_eq!(second.next, None);
+        assert_eq!(first.items[0].text, "e");
+        assert_eq!(second.items[1].text, "a");
+
+        seen.sort();
+        assert_eq!(seen, ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_list_after_bare_timestamp() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let created_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        for (i, text) in ["a", "b", "c"].into_iter().enumerate() {
+            let id = repo.create(text).unwrap().id;
+            repo.items.get_mut(&id).unwrap().created_at =
+                created_at + Duration::from_secs([0, 1, 1][i]);
+        }
+
+        let after = TodoCursor {
+            created_at: created_at + Duration::from_secs(1),
+            id: Uuid::nil(),
+        };
+
+        // Act
+        let page = repo.list_after(&TodoListFilter::All, Some(after), 10);
+
+        // Assert
+        let texts = page
+            .items
+            .into_iter()
+            .map(|todo| todo.text)
+            .collect::<Vec<_>>();
+
+        assert_eq!(texts, ["a"]);
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn test_update_stamps_updated_at() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
+
+        // Act
+        let unchanged = repo.set_completed(&todo.id, false).unwrap().todo;
+        let changed = repo.set_completed(&todo.id, true).unwrap().todo;
+
+        // Assert
+        assert_eq!(todo.updated_at, None);
+        assert_eq!(unchanged.updated_at, None);
+        assert!(changed.updated_at.is_some());
+        assert!(changed.last_modified() >= todo.created_at);
+    }
+
+    #[test]
+    fn test_append_text() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("write report").unwrap();
+
+        // Act
+        let outcome = repo.append_text(&todo.id, " outline done ").unwrap();
+
+        // Assert
+        assert_eq!(outcome.todo.text, "write report; outline done");
+        assert_eq!(outcome.todo.revision, todo.revision + 1);
+        assert_eq!(repo.get(&todo.id), Ok(outcome.todo));
+    }
+
+    #[test]
+    fn test_append_text_past_max_len() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create(&"a".repeat(MAX_TEXT_LEN - 3)).unwrap();
+
+        // Act
+        let fits = repo.append_text(&todo.id, "b");
+        let too_long = repo.append_text(&todo.id, "c");
+
+        // Assert
+        assert!(fits.is_ok());
+        assert_eq!(too_long, Err(TodoRepoError::TextTooLong));
+        assert_eq!(
+            repo.get(&todo.id).unwrap().text.chars().count(),
+            MAX_TEXT_LEN
+        );
+    }
+
+    #[test]
+    fn test_update_blank_text() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
+        let version = repo.version();
+
+        // Act
+        let result = repo.update(
+            &todo.id,
+            TodoUpdate {
+                text: Some(String::from("  ")),
+                is_completed: Some(true),
+                ..Default::default()
+            },
+        );
+
+        // Assert
+        assert_eq!(result, Err(TodoRepoError::BlankText));
+        assert_eq!(repo.get(&todo.id), Ok(todo));
+        assert_eq!(repo.version(), version);
+    }
+
+    #[test]
+    fn test_create_and_update_invalid_text() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
+        let too_long = "a".repeat(MAX_TEXT_LEN + 1);
+
+        // Act
+        let created_blank = repo.create(" \n ");
+        let created_too_long = repo.create(&too_long);
+        let updated_too_long = repo.update(
+            &todo.id,
+            TodoUpdate {
+                text: Some(too_long.clone()),
+                ..Default::default()
+            },
+        );
+
+        // Assert
+        assert_eq!(created_blank, Err(TodoRepoError::BlankText));
+        assert_eq!(created_too_long, Err(TodoRepoError::TextTooLong));
+        assert_eq!(updated_too_long, Err(TodoRepoError::TextTooLong));
+        assert_eq!(repo.get(&todo.id), Ok(todo));
+        assert_eq!(repo.num_all_items, 1);
+    }
+
+    #[test]
+    fn test_create_and_update_normalize_text() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+
+        // Act
+        let todo = repo.create("  a   b  ").unwrap();
+        let outcome = repo
+            .update(
+                &todo.id,
+                TodoUpdate {
+                    text: Some(String::from("\tc \n d ")),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        // Assert
+        assert_eq!(todo.text, "a b");
+        assert_eq!(outcome.todo.text, "c d");
+    }
+
+    #[test]
+    fn test_create_without_normalize_keeps_text() {
+        // Arrange
+        let mut repo = TodoRepo {
+            normalize: false,
+            ..Default::default()
+        };
+
+        // Act
+        let todo = repo.create("  a   b  ").unwrap();
+
+        // Assert
+        assert_eq!(todo.text, "  a   b  ");
+    }
+
+    #[test]
+    fn test_query_search_and_completed() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+
+        for (text, is_completed) in [
+            ("Buy milk", true),
+            ("buy bread", false),
+            ("Call mom", false),
+            ("buy MILK again", false),
+        ] {
+            let id = repo.create(text).unwrap().id;
+            repo.set_completed(&id, is_completed).unwrap();
+        }
+
+        let texts = |search: &str, completed| {
+            repo.query(&QueryParams {
+                search: Some(String::from(search)),
+                completed,
+                ..Default::default()
+            })
+            .items
+            .into_iter()
+            .map(|todo| todo.text)
+            .collect::<Vec<_>>()
+        };
+
+        // Act
+        let open_milk = texts("milk", Some(false));
+        let done_milk = texts(" MILK ", Some(true));
+        let open_buys = texts("buy", Some(false));
+        let any_blank = texts("  ", None);
+
+        // Assert
+        assert_eq!(open_milk, ["buy MILK again"]);
+        assert_eq!(done_milk, ["Buy milk"]);
+        assert_eq!(open_buys, ["buy MILK again", "buy bread"]);
+        assert_eq!(any_blank.len(), 4);
+    }
+
+    #[test]
+    fn test_query_paged_search() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+
+        for text in ["a1", "b", "a2", "a3"] {
+            repo.create(text).unwrap();
+        }
+
+        let params = QueryParams {
+            search: Some(String::from("a")),
+            limit: Some(2),
+            ..Default::default()
+        };
+
+        // Act
+        let first = repo.query(&params);
+        let second = repo.query(&QueryParams {
+            after: first.next,
+            ..params.clone()
+        });
+
+        // Assert
+        let texts = |page: TodoPage| {
+            page.items
+                .into_iter()
+                .map(|todo| todo.text)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(first.next.is_some());
+        assert_eq!(texts(first), ["a3", "a2"]);
+        assert_eq!(second.next, None);
+        assert_eq!(texts(second), ["a1"]);
+    }
+
+    #[test]
+    fn test_query_has_due() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+
+        for (text, due_at) in [
+            ("scheduled", Some(SystemTime::now())),
+            ("unscheduled", None),
+            (
+                "also scheduled",
+                Some(SystemTime::now() + Duration::from_secs(60)),
+            ),
+        ] {
+            let id = repo.create(text).unwrap().id;
+            repo.update(
+                &id,
+                TodoUpdate {
+                    due_at: Some(due_at),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        let texts = |has_due| {
+            repo.query(&QueryParams {
+                has_due,
+                ..Default::default()
+            })
+            .items
+            .into_iter()
+            .map(|todo| todo.text)
+            .collect::<Vec<_>>()
+        };
+
+        // Act
+        let scheduled = texts(Some(true));
+        let unscheduled = texts(Some(false));
+
+        // Assert
+        assert_eq!(scheduled, ["also scheduled", "scheduled"]);
+        assert_eq!(unscheduled, ["unscheduled"]);
+    }
+
+    #[test]
+    fn test_search_fuzzy_tolerates_typos() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+
+        for text in ["Buy milk", "Buy bread", "Call mom", "Buy silky"] {
+            repo.create(text).unwrap();
+        }
+
+        let params = |search: &str| QueryParams {
+            search: Some(String::from(search)),
+            ..Default::default()
+        };
+        let texts = |todos: Vec<Todo>| todos.into_iter().map(|todo| todo.text).collect::<Vec<_>>();
+
+        // Act
+        let exact = repo.query(&params("brexd")).items;
+        let fuzzy = repo.search_fuzzy(&params("brexd"));
+        let ranked = repo.search_fuzzy(&params("buy milk"));
+        let short = repo.search_fuzzy(&params("mox"));
+
+        // Assert
+        assert!(exact.is_empty());
+        assert_eq!(texts(fuzzy), ["Buy bread"]);
+        assert_eq!(texts(ranked), ["Buy milk", "Buy silky"]);
+        assert!(short.is_empty());
+    }
+
+    #[test]
+    fn test_list_at_date_windows() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(30 * 24 * 60 * 60);
+        let ages = [
+            ("fresh", Duration::from_secs(60)),
+            ("almost a day", Duration::from_secs(24 * 60 * 60 - 1)),
+            ("a day", Duration::from_secs(24 * 60 * 60)),
+            ("almost a week", Duration::from_secs(7 * 24 * 60 * 60 - 1)),
+            ("a week", Duration::from_secs(7 * 24 * 60 * 60)),
+        ];
+
+        for (text, age) in ages {
+            let id = repo.create(text).unwrap().id;
+            repo.items.get_mut(&id).unwrap().created_at = now - age;
+        }
+
+        let texts = |filter| {
+            repo.list_at(&filter, now)
+                .into_iter()
+                .map(|todo| todo.text)
+                .collect::<Vec<_>>()
+        };
+
+        // Act
+        let today = texts(TodoListFilter::Today);
+        let this_week = texts(TodoListFilter::ThisWeek);
+
+        // Assert
+        assert_eq!(today, ["fresh", "almost a day"]);
+        assert_eq!(
+            this_week,
+            ["fresh", "almost a day", "a day", "almost a week"]
+        );
+    }
+
+    #[test]
+    fn test_list_at_overdue() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(30 * 24 * 60 * 60);
+        let todos = [
+            ("past", Some(now - Duration::from_secs(1)), false),
+            ("now", Some(now), false),
+            ("future", Some(now + Duration::from_secs(60)), false),
+            ("absent", None, false),
+            ("past but done", Some(now - Duration::from_secs(60)), true),
+        ];
+
+        for (text, due_at, is_completed) in todos {
+            let id = repo.create(text).unwrap().id;
+            repo.update(
+                &id,
+                TodoUpdate {
+                    is_completed: Some(is_completed),
+                    due_at: Some(due_at),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        // Act
+        let overdue = repo.list_at(&TodoListFilter::Overdue, now);
+
+        // Assert
+        assert_eq!(
+            overdue
+                .into_iter()
+                .map(|todo| todo.text)
+                .collect::<Vec<_>>(),
+            ["past"]
+        );
+    }
+
+    #[test]
+    fn test_sorted_items_completed_last() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let created_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+
+        for (i, text) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            let id = repo.create(text).unwrap().id;
+            repo.items.get_mut(&id).unwrap().created_at =
+                created_at + Duration::from_secs(i as u64);
+
+            if i % 2 == 0 {
+                repo.set_completed(&id, true).unwrap();
+            }
+        }
 
         // Act
-        let result = repo.get(&id);
+        let grouped = repo.sorted_items(&TodoListFilter::All, TodoSort::CompletedLast);
+        let newest = repo.list(&TodoListFilter::All);
 
         // Assert
-        assert_eq!(result, Err(TodoRepoError::NotFound));
+        let grouped = grouped
+            .into_iter()
+            .map(|todo| todo.text)
+            .collect::<Vec<_>>();
+        let newest = newest.into_iter().map(|todo| todo.text).collect::<Vec<_>>();
+
+        assert_eq!(grouped, ["d", "b", "c", "a"]);
+        assert_eq!(newest, ["d", "c", "b", "a"]);
     }
 
     #[test]
-    fn test_get_existing_todo() {
+    fn test_reorder_shifts_neighbors() {
         // Arrange
-        let todo = Todo::new("test");
-        let id = Uuid::new_v4();
-
-        let repo = TodoRepo {
-            items: HashMap::from([(id, todo.clone())]),
-            ..Default::default()
-        };
+        let mut repo = TodoRepo::default();
+        let a = repo.create("a").unwrap();
+        let b = repo.create("b").unwrap();
+        let c = repo.create("c").unwrap();
+        let d = repo.create("d").unwrap();
 
         // Act
-        let result = repo.get(&id);
+        repo.reorder(&d.id, 0).unwrap();
+        repo.reorder(&a.id, 2).unwrap();
 
         // Assert
-        assert_eq!(result, Ok(todo));
+        let texts = repo
+            .sorted_items(&TodoListFilter::All, TodoSort::Manual)
+            .into_iter()
+            .map(|todo| (todo.text, todo.position))
+            .collect::<Vec<_>>();
+
+        assert_eq!(texts, [(d.text, 0), (b.text, 1), (a.text, 2), (c.text, 3)]);
     }
 
     #[test]
-    fn test_list_empty_repo() {
+    fn test_reorder_clamps_past_the_end() {
         // Arrange
-        let repo = TodoRepo::default();
-        let empty = Vec::new();
+        let mut repo = TodoRepo::default();
+        let a = repo.create("a").unwrap();
+        let b = repo.create("b").unwrap();
+        let c = repo.create("c").unwrap();
+        repo.delete(&b.id).unwrap();
 
         // Act
-        let result_completed = repo.list(&TodoListFilter::Completed);
-        let result_active = repo.list(&TodoListFilter::Active);
-        let result_all = repo.list(&TodoListFilter::All);
+        let moved = repo.reorder(&a.id, 10).unwrap();
 
         // Assert
-        assert_eq!(result_completed, empty);
-        assert_eq!(result_active, empty);
-        assert_eq!(result_all, empty);
+        assert_eq!(moved.position, 1);
+        assert_eq!(moved.revision, a.revision + 1);
+        assert_eq!(repo.get(&c.id).unwrap().position, 0);
+        assert_eq!(repo.reorder(&b.id, 0), Err(TodoRepoError::NotFound));
     }
 
     #[test]
-    fn test_list_filled_repo_active() {
+    fn test_reorder_missing_or_trashed_todo() {
         // Arrange
-        let todo_a = Todo::new("a");
-        let todo_b = Todo::new("b");
-        let todo_c = Todo::new("c");
-
-        let filled = vec![todo_c.clone(), todo_b.clone(), todo_a.clone()];
-        let empty = Vec::new();
-
-        let repo = TodoRepo {
-            items: HashMap::from([
-                (Uuid::new_v4(), todo_a),
-                (Uuid::new_v4(), todo_b),
-                (Uuid::new_v4(), todo_c),
-            ]),
-            ..Default::default()
-        };
+        let mut repo = TodoRepo::default();
+        let id = repo.create("a").unwrap().id;
+        repo.delete(&id).unwrap();
 
         // Act
-        let result_completed = repo.list(&TodoListFilter::Completed);
-        let result_active = repo.list(&TodoListFilter::Active);
-        let result_all = repo.list(&TodoListFilter::All);
+        let trashed = repo.reorder(&id, 0);
+        let missing = repo.reorder(&Uuid::new_v4(), 0);
 
         // Assert
-        assert_eq!(result_completed, empty);
-        assert_eq!(result_active, filled);
-        assert_eq!(result_all, filled);
+        assert_eq!(trashed, Err(TodoRepoError::NotFound));
+        assert_eq!(missing, Err(TodoRepoError::NotFound));
     }
 
     #[test]
-    fn test_list_filled_repo_completed() {
+    fn test_create_appends_to_manual_order() {
         // Arrange
-        let mut todo_a = Todo::new("a");
-        let mut todo_b = Todo::new("b");
-        let todo_c = Todo::new("c");
-
-        todo_a.is_completed = true;
-        todo_b.is_completed = true;
-
-        let completed = vec![todo_b.clone(), todo_a.clone()];
-        let active = vec![todo_c.clone()];
-        let all = vec![todo_c.clone(), todo_b.clone(), todo_a.clone()];
-
-        let repo = TodoRepo {
-            items: HashMap::from([
-                (Uuid::new_v4(), todo_a),
-                (Uuid::new_v4(), todo_b),
-                (Uuid::new_v4(), todo_c),
-            ]),
-            ..Default::default()
-        };
+        let mut repo = TodoRepo::default();
+        let a = repo.create("a").unwrap();
+        repo.create("b").unwrap();
+        repo.reorder(&a.id, 1).unwrap();
 
         // Act
-        let result_completed = repo.list(&TodoListFilter::Completed);
-        let result_active = repo.list(&TodoListFilter::Active);
-        let result_all = repo.list(&TodoListFilter::All);
+        let c = repo.create("c").unwrap();
 
         // Assert
-        assert_eq!(result_completed, completed);
-        assert_eq!(result_active, active);
-        assert_eq!(result_all, all);
+        assert_eq!(c.position, 2);
     }
 
     #[test]
-    fn test_create_todo() {
+    fn test_duplicate() {
         // Arrange
-        let mut repo = TodoRepo {
-            items: HashMap::from([(Uuid::new_v4(), Todo::new("a"))]),
-            num_completed_items: 0,
-            num_active_items: 1,
-            num_all_items: 1,
-        };
+        let mut repo = TodoRepo::default();
+        let original = repo.create("write report").unwrap();
+        repo.update(
+            &original.id,
+            TodoUpdate {
+                description: Some(String::from("quarterly")),
+                tags: Some(vec![String::from("work")]),
+                color: Some(String::from("red")),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        repo.add_subtask(&original.id, "outline").unwrap();
+        let subtask = repo.get(&original.id).unwrap().subtasks[0].id;
+        repo.toggle_subtask(&original.id, &subtask).unwrap();
+        repo.set_completed(&original.id, true).unwrap();
 
         // Act
-        let result = repo.create("new");
+        let copy = repo.duplicate(&original.id).unwrap();
 
         // Assert
-        assert_eq!(result.text, "new".to_string());
-        assert!(!result.is_completed);
-
-        assert_eq!(repo.num_completed_items, 0);
-        assert_eq!(repo.num_active_items, 2);
-        assert_eq!(repo.num_all_items, 2);
+        let original = repo.get(&original.id).unwrap();
+
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.text, "write report");
+        assert_eq!(copy.status, TodoStatus::Todo);
+        assert_eq!(copy.completed_at, None);
+        assert_eq!(copy.description.as_deref(), Some("quarterly"));
+        assert_eq!(copy.tags, ["work"]);
+        assert_eq!(copy.color.as_deref(), Some("red"));
+        assert_eq!(copy.subtasks.len(), 1);
+        assert_ne!(copy.subtasks[0].id, subtask);
+        assert!(!copy.subtasks[0].done);
+        assert_eq!(repo.get(&copy.id).unwrap(), copy);
+        assert_eq!(repo.counters(), (1, 1, 2));
+        assert_eq!(
+            repo.duplicate(&Uuid::new_v4()),
+            Err(TodoRepoError::NotFound)
+        );
     }
 
     #[test]
-    fn test_delete_non_existing_todo() {
+    fn test_roll_recurring_daily_todo() {
         // Arrange
         let mut repo = TodoRepo::default();
-        let id = Uuid::new_v4();
+        let todo = repo.create("stretch").unwrap();
+        repo.update(
+            &todo.id,
+            TodoUpdate {
+                is_completed: Some(true),
+                recurrence: Some(Some(Recurrence::Daily)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let completed_at = repo.get(&todo.id).unwrap().completed_at.unwrap();
+        let day = Duration::from_secs(24 * 60 * 60);
 
         // Act
-        let result = repo.delete(&id);
+        let too_early = repo.roll_recurring(completed_at + day / 2);
+        let rolled = repo.roll_recurring(completed_at + day);
+        let rolled_again = repo.roll_recurring(completed_at + 2 * day);
 
         // Assert
-        assert_eq!(result, Err(TodoRepoError::NotFound));
+        assert!(too_early.is_empty());
+        assert_eq!(rolled.len(), 1);
+        assert!(rolled_again.is_empty());
+
+        let fresh = &rolled[0];
+        assert_ne!(fresh.id, todo.id);
+        assert_eq!(fresh.text, "stretch");
+        assert!(!fresh.is_completed());
+        assert_eq!(fresh.recurrence, Some(Recurrence::Daily));
+        assert_eq!(repo.get(&todo.id).unwrap().recurrence, None);
+        assert_eq!(repo.counters(), (1, 1, 2));
     }
 
     #[test]
-    fn test_delete_existing_todo() {
+    fn test_roll_recurring_skips_active_and_plain_todos() {
         // Arrange
-        let id = Uuid::new_v4();
-
-        let mut repo = TodoRepo {
-            items: HashMap::from([(id, Todo::new("a")), (Uuid::new_v4(), Todo::new("b"))]),
-            num_completed_items: 0,
-            num_active_items: 2,
-            num_all_items: 2,
-        };
+        let mut repo = TodoRepo::default();
+        let active = repo.create("a").unwrap();
+        let plain = repo.create("b").unwrap();
+        repo.update(
+            &active.id,
+            TodoUpdate {
+                recurrence: Some(Some(Recurrence::Daily)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        repo.set_completed(&plain.id, true).unwrap();
 
         // Act
-        let result = repo.delete(&id);
+        let rolled =
+            repo.roll_recurring(SystemTime::now() + Duration::from_secs(30 * 24 * 60 * 60));
 
         // Assert
-        assert_eq!(repo.num_completed_items, 0);
-        assert_eq!(repo.num_active_items, 1);
-        assert_eq!(repo.num_all_items, 1);
-
-        assert_eq!(result, Ok(()));
+        assert!(rolled.is_empty());
+        assert_eq!(repo.counters(), (1, 1, 2));
     }
 
     #[test]
-    fn test_update_non_existing_todo() {
+    fn test_version_advances_on_changes() {
         // Arrange
         let mut repo = TodoRepo::default();
-        let id = Uuid::new_v4();
+        let initial = repo.version();
 
         // Act
-        let result = repo.update(&id, None, None);
+        let todo = repo.create("a").unwrap();
+        let created = repo.version();
+
+        repo.set_completed(&todo.id, true).unwrap();
+        let updated = repo.version();
+
+        repo.set_completed(&todo.id, true).unwrap();
+        let unchanged = repo.version();
+
+        repo.delete(&todo.id).unwrap();
+        let deleted = repo.version();
 
         // Assert
-        assert_eq!(result, Err(TodoRepoError::NotFound));
+        assert_eq!(initial, 0);
+        assert!(created > initial);
+        assert!(updated > created);
+        assert_eq!(unchanged, updated);
+        assert!(deleted > updated);
     }
 
     #[test]
-    fn test_update_text_existing_todo() {
+    fn test_create_many_skips_blank_and_invalid_texts() {
         // Arrange
-        let todo = Todo::new("test");
-        let id = Uuid::new_v4();
-
-        let mut repo = TodoRepo {
-            items: HashMap::from([(id, todo.clone())]),
-            num_completed_items: 0,
-            num_active_items: 1,
-            num_all_items: 1,
-        };
+        let mut repo = TodoRepo::with_capacity(3);
+        let texts = [
+            " a ",
+            "",
+            "   ",
+            &"x".repeat(MAX_TEXT_LEN + 1),
+            "b",
+            "c",
+            "d",
+        ]
+        .map(String::from);
 
         // Act
-        let result = repo.update(&id, Some("update".to_string()), None);
+        let created = repo.create_many(&texts, TodoSource::Api);
 
         // Assert
-        assert!(result.is_ok());
-
-        if let Ok(update) = result {
-            assert_eq!(update.is_completed, todo.is_completed);
-            assert_eq!(update.created_at, todo.created_at);
-            assert_eq!(update.text, "update".to_string());
-            assert_eq!(update.id, todo.id);
-        }
+        let texts = created
+            .iter()
+            .map(|todo| todo.text.as_str())
+            .collect::<Vec<_>>();
 
-        assert_eq!(repo.num_completed_items, 0);
-        assert_eq!(repo.num_active_items, 1);
-        assert_eq!(repo.num_all_items, 1);
+        assert_eq!(texts, ["a", "b", "c"]);
+        assert!(created.iter().all(|todo| todo.source == TodoSource::Api));
+        assert_eq!(repo.counters(), (0, 3, 3));
     }
 
     #[test]
-    fn test_update_is_completed_true_existing_todo() {
+    fn test_create_many_skips_duplicates() {
         // Arrange
-        let todo = Todo::new("test");
-        let id = Uuid::new_v4();
-
-        let mut repo = TodoRepo {
-            items: HashMap::from([(id, todo.clone())]),
-            num_completed_items: 0,
-            num_active_items: 1,
-            num_all_items: 1,
-        };
+        let mut repo = TodoRepo::default();
+        repo.create("a").unwrap();
+        let texts = ["a", "b", " b ", "c"].map(String::from);
 
         // Act
-        let result = repo.update(&id, None, Some(true));
+        let created = repo.create_many(&texts, TodoSource::Api);
 
         // Assert
-        assert!(result.is_ok());
+        let texts = created
+            .iter()
+            .map(|todo| todo.text.as_str())
+            .collect::<Vec<_>>();
 
-        if let Ok(update) = result {
-            assert_eq!(update.created_at, todo.created_at);
-            assert_eq!(update.text, todo.text);
-            assert_eq!(update.id, todo.id);
-            assert!(update.is_completed);
-        }
+        assert_eq!(texts, ["b", "c"]);
+        assert_eq!(repo.counters(), (0, 3, 3));
+    }
 
-        assert_eq!(repo.num_completed_items, 1);
-        assert_eq!(repo.num_active_items, 0);
-        assert_eq!(repo.num_all_items, 1);
+    #[test]
+    fn test_completion_pct() {
+        let cases = [
+            (0, 0, 0),
+            (0, 3, 0),
+            (1, 3, 33),
+            (2, 3, 66),
+            (199, 200, 99),
+            (3, 3, 100),
+        ];
+
+        for (num_completed_items, num_all_items, expected) in cases {
+            let repo = TodoRepo {
+                num_completed_items,
+                num_active_items: num_all_items - num_completed_items,
+                num_all_items,
+                ..Default::default()
+            };
+
+            assert_eq!(
+                repo.completion_pct(),
+                expected,
+                "{num_completed_items}/{num_all_items}"
+            );
+        }
     }
 
     #[test]
-    fn test_update_is_completed_false_existing_todo() {
+    fn test_delete_then_restore() {
         // Arrange
-        let mut todo = Todo::new("test");
-        let id = Uuid::new_v4();
-
-        todo.is_completed = true;
-
-        let mut repo = TodoRepo {
-            items: HashMap::from([(id, todo.clone())]),
-            num_completed_items: 1,
-            num_active_items: 0,
-            num_all_items: 1,
-        };
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
+        repo.create("b").unwrap();
 
         // Act
-        let result = repo.update(&id, None, Some(false));
+        repo.delete(&todo.id).unwrap();
+        let trashed = repo.list(&TodoListFilter::Trash);
+        let counters_trashed = repo.counters();
+        let restored = repo.restore(&todo.id).unwrap();
 
         // Assert
-        assert!(result.is_ok());
-
-        if let Ok(update) = result {
-            assert_eq!(update.created_at, todo.created_at);
-            assert_eq!(update.text, todo.text);
-            assert_eq!(update.id, todo.id);
-            assert!(!update.is_completed);
-        }
-
-        assert_eq!(repo.num_completed_items, 0);
-        assert_eq!(repo.num_active_items, 1);
-        assert_eq!(repo.num_all_items, 1);
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].id, todo.id);
+        assert!(trashed[0].deleted_at.is_some());
+        assert_eq!(counters_trashed, (0, 1, 1));
+
+        assert_eq!(restored.deleted_at, None);
+        assert!(repo.list(&TodoListFilter::Trash).is_empty());
+        assert_eq!(repo.list(&TodoListFilter::All).len(), 2);
+        assert_eq!(repo.counters(), (0, 2, 2));
+        assert_eq!(repo.restore(&todo.id), Err(TodoRepoError::NotFound));
     }
 
     #[test]
-    fn test_delete_completed_todos() {
+    fn test_trashed_todo_not_found() {
         // Arrange
-        let mut todo_a = Todo::new("a");
-        let mut todo_b = Todo::new("b");
-        let todo_c = Todo::new("c");
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
+        let subtask = repo.add_subtask(&todo.id, "b").unwrap().todo.subtasks[0].id;
+        repo.set_completed(&todo.id, true).unwrap();
+        repo.delete(&todo.id).unwrap();
 
-        todo_a.is_completed = true;
-        todo_b.is_completed = true;
+        // Act
+        let update = repo.update(
+            &todo.id,
+            TodoUpdate {
+                text: Some(String::from("c")),
+                ..Default::default()
+            },
+        );
 
-        let active = vec![todo_c.clone()];
+        // Assert
+        assert_eq!(update, Err(TodoRepoError::NotFound));
+        assert_eq!(repo.get(&todo.id), Err(TodoRepoError::NotFound));
+        assert_eq!(
+            repo.get_by_short(&todo.short_id()),
+            Err(TodoRepoError::NotFound)
+        );
+        assert_eq!(
+            repo.append_text(&todo.id, "d"),
+            Err(TodoRepoError::NotFound)
+        );
+        assert_eq!(
+            repo.add_subtask(&todo.id, "e"),
+            Err(TodoRepoError::NotFound)
+        );
+        assert_eq!(
+            repo.toggle_subtask(&todo.id, &subtask),
+            Err(TodoRepoError::NotFound)
+        );
+        assert_eq!(repo.duplicate(&todo.id), Err(TodoRepoError::NotFound));
+        assert_eq!(
+            repo.velocity(Duration::from_secs(3600), SystemTime::now()),
+            0.0
+        );
+        assert!(repo.restore(&todo.id).is_ok());
+    }
 
-        let mut repo = TodoRepo {
-            items: HashMap::from([
-                (Uuid::new_v4(), todo_a),
-                (Uuid::new_v4(), todo_b),
-                (Uuid::new_v4(), todo_c),
-            ]),
-            num_completed_items: 2,
-            num_active_items: 1,
-            num_all_items: 3,
-        };
+    #[test]
+    fn test_delete_then_purge() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
+        repo.create("b").unwrap();
+        repo.delete(&todo.id).unwrap();
 
         // Act
-        repo.delete_completed();
+        let purged = repo.purge();
 
         // Assert
-        assert_eq!(repo.items.into_values().collect::<Vec<_>>(), active);
-
-        assert_eq!(repo.num_completed_items, 0);
-        assert_eq!(repo.num_active_items, 1);
-        assert_eq!(repo.num_all_items, 1);
+        assert_eq!(purged, 1);
+        assert_eq!(repo.get(&todo.id), Err(TodoRepoError::NotFound));
+        assert_eq!(repo.delete(&todo.id), Err(TodoRepoError::NotFound));
+        assert!(repo.list(&TodoListFilter::Trash).is_empty());
+        assert_eq!(repo.counters(), (0, 1, 1));
     }
 
     #[test]
-    fn test_toggle_check_completed_todos() {
+    fn test_create_with_id_source() {
         // Arrange
-        let mut todo_a = Todo::new("a");
-        let mut todo_b = Todo::new("b");
-        let todo_c = Todo::new("c");
-        let id = Uuid::new_v4();
+        let mut next = 0;
+        let mut repo = TodoRepo::with_id_source(move || {
+            next += 1;
+            Uuid::from_u128(next)
+        });
 
-        todo_a.is_completed = true;
-        todo_b.is_completed = true;
+        // Act
+        let a = repo.create("a").unwrap();
+        let b = repo.create("b").unwrap();
 
-        let mut repo = TodoRepo {
-            items: HashMap::from([
-                (Uuid::new_v4(), todo_a),
-                (Uuid::new_v4(), todo_b),
-                (id, todo_c),
-            ]),
-            num_completed_items: 2,
-            num_active_items: 1,
-            num_all_items: 3,
-        };
+        // Assert
+        assert_eq!(a.id, Uuid::from_u128(1));
+        assert_eq!(b.id, Uuid::from_u128(2));
+        assert_eq!(repo.get(&Uuid::from_u128(2)), Ok(b));
+    }
+
+    #[test]
+    fn test_update_color() {
+        // Arrange
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
 
         // Act
-        repo.toggle_completed(&TodoToggleAction::Check);
+        let valid = repo.update(
+            &todo.id,
+            TodoUpdate {
+                color: Some(String::from("Green")),
+                ..Default::default()
+            },
+        );
+        let invalid = repo.update(
+            &todo.id,
+            TodoUpdate {
+                text: Some(String::from("b")),
+                color: Some(String::from("purple")),
+                ..Default::default()
+            },
+        );
 
         // Assert
-        assert!(repo.items.get(&id).unwrap().is_completed);
+        assert_eq!(valid.unwrap().todo.color.as_deref(), Some("green"));
+        assert_eq!(invalid, Err(TodoRepoError::InvalidColor));
 
-        assert_eq!(repo.num_completed_items, 3);
-        assert_eq!(repo.num_active_items, 0);
-        assert_eq!(repo.num_all_items, 3);
+        let stored = repo.get(&todo.id).unwrap();
+
+        assert_eq!(stored.text, "a");
+        assert_eq!(stored.color.as_deref(), Some("green"));
     }
 
     #[test]
-    fn test_toggle_uncheck_completed_todos() {
+    fn test_update_expected_revision() {
         // Arrange
-        let mut todo_a = Todo::new("a");
-        let todo_b = Todo::new("b");
-        let todo_c = Todo::new("c");
-        let id = Uuid::new_v4();
-
-        todo_a.is_completed = true;
-
-        let mut repo = TodoRepo {
-            items: HashMap::from([
-                (Uuid::new_v4(), todo_b),
-                (Uuid::new_v4(), todo_c),
-                (id, todo_a),
-            ]),
-            num_completed_items: 1,
-            num_active_items: 2,
-            num_all_items: 3,
-        };
+        let mut repo = TodoRepo::default();
+        let todo = repo.create("a").unwrap();
 
         // Act
-        repo.toggle_completed(&TodoToggleAction::Uncheck);
+        let matching = repo.update(
+            &todo.id,
+            TodoUpdate {
+                text: Some(String::from("b")),
+                expected_revision: Some(todo.revision),
+                ..Default::default()
+            },
+        );
+        let stale = repo.update(
+            &todo.id,
+            TodoUpdate {
+                text: Some(String::from("c")),
+                expected_revision: Some(todo.revision),
+                ..Default::default()
+            },
+        );
 
         // Assert
-        assert!(!repo.items.get(&id).unwrap().is_completed);
-
-        assert_eq!(repo.num_completed_items, 0);
-        assert_eq!(repo.num_active_items, 3);
-        assert_eq!(repo.num_all_items, 3);
+        assert_eq!(matching.unwrap().todo.revision, todo.revision + 1);
+        assert_eq!(stale, Err(TodoRepoError::Conflict));
+        assert_eq!(repo.get(&todo.id).unwrap().text, "b");
     }
 }
@@ -0,0 +1,197 @@
+use std::time::{Duration, SystemTime};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+
+/// Formats a duration using its largest whole unit, e.g. "45s", "5m", "2h" or "3d".
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+
+    if secs < MINUTE {
+        format!("{}s", secs)
+    } else if secs < HOUR {
+        format!("{}m", secs / MINUTE)
+    } else if secs < DAY {
+        format!("{}h", secs / HOUR)
+    } else {
+        format!("{}d", secs / DAY)
+    }
+}
+
+/// How long ago `time` was, e.g. "just now", "5m ago" or "3d ago".
+pub fn relative_time(time: SystemTime) -> String {
+    relative_time_since(time, SystemTime::now())
+}
+
+/// Like `relative_time`, measured from `now`; times in the future read "just now".
+pub fn relative_time_since(time: SystemTime, now: SystemTime) -> String {
+    match now.duration_since(time) {
+        Ok(elapsed) if elapsed.as_secs() >= MINUTE => format!("{} ago", format_duration(elapsed)),
+        _ => String::from("just now"),
+    }
+}
+
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Writes `n` in base62, e.g. "0", "Z" or "10" for 62.
+pub fn encode_base62(mut n: u64) -> String {
+    let mut digits = Vec::new();
+
+    loop {
+        digits.push(BASE62[(n % 62) as usize]);
+        n /= 62;
+
+        if n == 0 {
+            break;
+        }
+    }
+
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// Reads what `encode_base62` writes, refusing leading zeros so every number has one spelling.
+pub fn decode_base62(text: &str) -> Option<u64> {
+    if text.is_empty() || (text.len() > 1 && text.starts_with('0')) {
+        return None;
+    }
+
+    text.bytes().try_fold(0u64, |n, byte| {
+        let digit = BASE62.iter().position(|&b| b == byte)?;
+        n.checked_mul(62)?.checked_add(digit as u64)
+    })
+}
+
+/// Levenshtein distance from `pattern` to the closest part of `text`, so 0 whenever `text`
+/// contains `pattern`.
+pub fn substring_distance(pattern: &str, text: &str) -> usize {
+    let text = text.chars().collect::<Vec<_>>();
+    // Starting at 0 everywhere lets the match begin anywhere in `text`.
+    let mut row = vec![0; text.len() + 1];
+
+    for (i, p) in pattern.chars().enumerate() {
+        let mut next = Vec::with_capacity(row.len());
+        next.push(i + 1);
+
+        for (j, &t) in text.iter().enumerate() {
+            let substitution = row[j] + usize::from(p != t);
+            next.push(substitution.min(row[j + 1] + 1).min(next[j] + 1));
+        }
+
+        row = next;
+    }
+
+    row.into_iter().min().unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        // Arrange
+        let cases = [
+            (0, "0s"),
+            (59, "59s"),
+            (MINUTE, "1m"),
+            (HOUR - 1, "59m"),
+            (2 * HOUR, "2h"),
+            (DAY - 1, "23h"),
+            (3 * DAY, "3d"),
+        ];
+
+        for (secs, expected) in cases {
+            // Act
+            let result = format_duration(Duration::from_secs(secs));
+
+            // Assert
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_relative_time_since() {
+        // Arrange
+        let now = SystemTime::now();
+        let cases = [
+            (0, "just now"),
+            (MINUTE - 1, "just now"),
+            (MINUTE, "1m ago"),
+            (HOUR - 1, "59m ago"),
+            (HOUR, "1h ago"),
+            (DAY - 1, "23h ago"),
+            (DAY, "1d ago"),
+            (3 * DAY, "3d ago"),
+        ];
+
+        for (secs, expected) in cases {
+            // Act
+            let result = relative_time_since(now - Duration::from_secs(secs), now);
+
+            // Assert
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_base62() {
+        // Arrange
+        let cases = [
+            (0, "0"),
+            (9, "9"),
+            (35, "Z"),
+            (61, "z"),
+            (62, "10"),
+            (u64::MAX, "LygHa16AHYF"),
+        ];
+
+        for (n, expected) in cases {
+            // Act
+            let encoded = encode_base62(n);
+
+            // Assert
+            assert_eq!(encoded, expected);
+            assert_eq!(decode_base62(&encoded), Some(n));
+        }
+
+        for invalid in ["", "00", "01", "a-b", "LygHa16AHYG"] {
+            assert_eq!(decode_base62(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_relative_time_since_future() {
+        // Arrange
+        let now = SystemTime::now();
+
+        // Act
+        let result = relative_time_since(now + Duration::from_secs(HOUR), now);
+
+        // Assert
+        assert_eq!(result, "just now");
+    }
+
+    #[test]
+    fn test_substring_distance() {
+        // Arrange
+        let cases = [
+            ("milk", "buy milk", 0),
+            ("", "buy milk", 0),
+            ("mylk", "buy milk", 1),
+            ("mlk", "buy milk", 1),
+            ("miilk", "buy milk", 1),
+            ("bread", "buy milk", 4),
+            ("milk", "", 4),
+        ];
+
+        for (pattern, text, expected) in cases {
+            // Act
+            let result = substring_distance(pattern, text);
+
+            // Assert
+            assert_eq!(result, expected, "{pattern} in {text}");
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo,
+    },
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::atomic::Ordering,
+    time::Instant,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::models::{is_valid_text, TodoEvent, TodoSource, MAX_TEXT_LEN};
+use crate::repository::TodoRepoError;
+use crate::{record_completion_change, AppError, Metrics, StateLock};
+
+/// A message a client sends over `/todo/ws`, e.g. `{"type": "create", "text": "a"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsCommand {
+    Create {
+        text: String,
+    },
+    /// Flips whether the todo is completed.
+    Toggle {
+        id: Uuid,
+    },
+    Delete {
+        id: Uuid,
+    },
+}
+
+/// A message the server sends over `/todo/ws`, e.g. `{"type": "created", "id": "…"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WsEvent {
+    Created {
+        id: Uuid,
+    },
+    Updated {
+        id: Uuid,
+    },
+    Deleted {
+        id: Uuid,
+    },
+    Restored {
+        id: Uuid,
+    },
+    /// Several todos changed at once; `event` is named as on `/todo/events`.
+    Changed {
+        event: String,
+    },
+    /// A command failed; only the socket that sent it is told.
+    Error {
+        error: String,
+    },
+}
+
+impl From<TodoEvent> for WsEvent {
+    fn from(event: TodoEvent) -> Self {
+        match event {
+            TodoEvent::Created(id) => Self::Created { id },
+            TodoEvent::Updated(id) => Self::Updated { id },
+            TodoEvent::Deleted(id) => Self::Deleted { id },
+            TodoEvent::Restored(id) => Self::Restored { id },
+            TodoEvent::Toggled
+            | TodoEvent::DeletedCompleted
+            | TodoEvent::Cleared
+            | TodoEvent::Deduplicated
+            | TodoEvent::Imported
+            | TodoEvent::Transaction
+            | TodoEvent::Purged => Self::Changed {
+                event: event.to_string(),
+            },
+        }
+    }
+}
+
+pub(crate) async fn todo_socket(
+    state_lock: StateLock,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, AppError> {
+    let client = connect_info.map(|ConnectInfo(addr)| addr.ip());
+    let events = state_lock.read().await?.events.subscribe();
+
+    Ok(upgrade.on_upgrade(move |socket| handle_socket(state_lock, client, events, socket)))
+}
+
+async fn handle_socket(
+    state_lock: StateLock,
+    client: Option<IpAddr>,
+    mut events: broadcast::Receiver<TodoEvent>,
+    mut socket: WebSocket,
+) {
+    loop {
+        let reply = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                    Ok(command) => apply(&state_lock, client, command).await.err(),
+                    Err(err) => Some(WsEvent::Error {
+                        error: err.to_string(),
+                    }),
+                },
+                Some(Ok(_)) => None,
+                Some(Err(_)) | None => break,
+            },
+            event = events.recv() => match event {
+                Ok(event) => Some(event.into()),
+                // Like `/todo/events`, a lagging socket misses what it could not keep up with.
+                Err(RecvError::Lagged(_)) => None,
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if let Some(reply) = reply {
+            let text = serde_json::to_string(&reply).unwrap();
+
+            if socket.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Runs `command`; its event reaches every socket, this one included, through the broadcast.
+///
+/// Every command is a write, charged to `client` like a write over HTTP.
+async fn apply(
+    state_lock: &StateLock,
+    client: Option<IpAddr>,
+    command: WsCommand,
+) -> Result<(), WsEvent> {
+    let mut state = state_lock.write().await.map_err(|err| WsEvent::Error {
+        error: err.describe().2,
+    })?;
+
+    if state.read_only.load(Ordering::Relaxed) {
+        return Err(WsEvent::Error {
+            error: AppError::ReadOnly.describe().2,
+        });
+    }
+
+    if let Some(limiter) = &mut state.write_limiter {
+        if !limiter.check(client, Instant::now()) {
+            return Err(WsEvent::Error {
+                error: String::from("Too many writes, try again later"),
+            });
+        }
+    }
+
+    match command {
+        WsCommand::Create { text } => {
+            if !is_valid_text(&text) {
+                return Err(WsEvent::Error {
+                    error: format!(
+                        "Todo texts must not be blank and are limited to {} characters",
+                        MAX_TEXT_LEN
+                    ),
+                });
+            }
+
+            let item = state
+                .todo_repo
+                .create_unique_from(&text, TodoSource::Api)
+                .map_err(repo_error)?;
+
+            state.publish(TodoEvent::Created(item.id));
+            Metrics::add(&state.metrics.todos_created, 1);
+        }
+        WsCommand::Toggle { id } => {
+            let is_completed = state.todo_repo.get(&id).map_err(repo_error)?.is_completed();
+            let outcome = state
+                .todo_repo
+                .set_completed(&id, !is_completed)
+                .map_err(repo_error)?;
+
+            state.publish(TodoEvent::Updated(id));
+            record_completion_change(&state, &outcome);
+        }
+        WsCommand::Delete { id } => {
+            state.todo_repo.delete(&id).map_err(repo_error)?;
+            state.publish(TodoEvent::Deleted(id));
+            Metrics::add(&state.metrics.todos_deleted, 1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Words the error as the HTTP routes would.
+fn repo_error(err: TodoRepoError) -> WsEvent {
+    WsEvent::Error {
+        error: AppError::from(err).describe().2,
+    }
+}
@@ -1,15 +1,33 @@
 use axum::{
     body::{Body, HttpBody},
-    http::{Request, Response, StatusCode},
+    extract::ConnectInfo,
+    http::{HeaderValue, Request, Response, StatusCode},
 };
+use futures_util::{SinkExt, StreamExt};
 use scraper::{Html, Selector};
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    sync::{atomic::Ordering, mpsc},
+    thread,
+    time::{Duration, SystemTime},
+};
 use todomvc::{
     app,
-    models::{TodoListFilter, TodoToggleAction},
+    assets::AssetManifest,
+    models::{
+        Recurrence, Todo, TodoEvent, TodoListFilter, TodoSource, TodoStatus, TodoToggleAction,
+        TodoUpdate, MAX_DESCRIPTION_LEN, MAX_TEXT_LEN,
+    },
+    purge_old_completed,
+    rate_limit::RateLimiter,
+    repository::{TodoRepo, TodoRepoError},
+    roll_recurring,
+    ws::WsEvent,
     SharedState,
 };
+use tokio_tungstenite::tungstenite::Message;
 use tower::ServiceExt;
+use tower_cookies::{cookie::CookieJar, Cookie};
 
 async fn parse_response_body<T: HttpBody>(response: Response<T>) -> String
 where
@@ -19,146 +37,224 @@ where
     String::from_utf8(body.to_vec()).unwrap()
 }
 
+fn selected_filter_cookie<T>(response: &Response<T>) -> String {
+    let cookie = response.headers().get("Set-Cookie").unwrap();
+    let cookie = Cookie::parse(cookie.to_str().unwrap().to_string()).unwrap();
+
+    assert_eq!(cookie.name(), "todo_filter");
+    cookie.value().to_string()
+}
+
 #[tokio::test]
-async fn test_list_todo_empty() {
+async fn test_assets_dir() {
     // Arrange
+    let assets_dir = std::env::temp_dir().join(format!("todomvc-assets-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&assets_dir).unwrap();
+    std::fs::write(assets_dir.join("dummy.css"), "body {}").unwrap();
+
     let shared_state = SharedState::default();
-    let local_state = shared_state.clone();
-    let app = app(shared_state);
-    let request = Request::get("/todo?filter=All")
+    shared_state.write().unwrap().assets_dir = assets_dir.clone();
+
+    let request = Request::get("/assets/dummy.css")
         .body(Body::empty())
         .unwrap();
 
     // Act
-    let response = app.oneshot(request).await.unwrap();
+    let response = app(shared_state).oneshot(request).await.unwrap();
 
     // Assert
     assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(parse_response_body(response).await, "body {}");
 
-    assert_eq!(
-        local_state.read().unwrap().selected_filter,
-        TodoListFilter::All
-    );
+    std::fs::remove_dir_all(assets_dir).unwrap();
+}
 
-    let body = parse_response_body(response).await;
-    let document = Html::parse_document(&body);
+#[tokio::test]
+async fn test_assets_cache_control() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().assets_max_age = Duration::from_secs(60);
 
-    let counter_selector = Selector::parse(".todo-counter").unwrap();
-    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
-    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+    let app = app(shared_state);
+    let asset_request = Request::get("/assets/helpers.js")
+        .body(Body::empty())
+        .unwrap();
+    let list_request = Request::get("/todo").body(Body::empty()).unwrap();
 
-    assert_eq!(document.select(&counter_selector).count(), 3);
-    assert_eq!(document.select(&toggle_selector).count(), 1);
-    assert_eq!(document.select(&list_selector).count(), 0);
+    // Act
+    let asset_response = app.clone().oneshot(asset_request).await.unwrap();
+    let list_response = app.oneshot(list_request).await.unwrap();
 
-    assert!(document
-        .select(&toggle_selector)
-        .next()
-        .unwrap()
-        .value()
-        .attr("disabled")
-        .is_some());
+    // Assert
+    assert_eq!(asset_response.status(), StatusCode::OK);
+    assert_eq!(
+        asset_response.headers()["cache-control"],
+        "public, max-age=60"
+    );
+    assert_eq!(list_response.status(), StatusCode::OK);
+    assert!(list_response.headers().get("cache-control").is_none());
 }
 
 #[tokio::test]
-async fn test_list_todo_non_empty() {
+async fn test_index_renders_todos() {
     // Arrange
     let shared_state = SharedState::default();
-    let local_state = shared_state.clone();
 
     {
         let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
 
-        todo_repo.create("a");
-        todo_repo.create("b");
-        todo_repo.create("c");
+        todo_repo.create("b").unwrap();
+        todo_repo.set_completed(&todo.id, true).unwrap();
     }
 
-    let app = app(shared_state);
-    let request = Request::get("/todo?filter=Active")
-        .body(Body::empty())
-        .unwrap();
+    let request = Request::get("/").body(Body::empty()).unwrap();
 
     // Act
-    let response = app.oneshot(request).await.unwrap();
+    let response = app(shared_state).oneshot(request).await.unwrap();
 
     // Assert
     assert_eq!(response.status(), StatusCode::OK);
 
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let item_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+    let counter_selector = Selector::parse("#todo-counter-active").unwrap();
+    let delete_selector = Selector::parse("#todo-delete-completed[disabled]").unwrap();
+
+    let texts = document
+        .select(&item_selector)
+        .map(|item| item.text().collect::<String>().trim().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts, ["b", "a"]);
     assert_eq!(
-        local_state.read().unwrap().selected_filter,
-        TodoListFilter::Active
+        document
+            .select(&counter_selector)
+            .next()
+            .unwrap()
+            .text()
+            .collect::<String>()
+            .trim(),
+        "1"
     );
+    assert_eq!(document.select(&delete_selector).count(), 0);
+}
 
-    let body = parse_response_body(response).await;
-    let document = Html::parse_document(&body);
+#[tokio::test]
+async fn test_index_fingerprinted_assets() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().assets = AssetManifest::from_dir("assets").unwrap();
 
-    let counter_selector = Selector::parse(".todo-counter").unwrap();
-    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
-    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+    let request = Request::get("/").body(Body::empty()).unwrap();
+    let response = app(shared_state.clone()).oneshot(request).await.unwrap();
 
-    assert_eq!(document.select(&counter_selector).count(), 3);
-    assert_eq!(document.select(&toggle_selector).count(), 1);
-    assert_eq!(document.select(&list_selector).count(), 3);
+    assert_eq!(response.status(), StatusCode::OK);
 
-    assert!(document
-        .select(&toggle_selector)
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let script_selector = Selector::parse("script[src^='/assets/']").unwrap();
+    let src = document
+        .select(&script_selector)
         .next()
         .unwrap()
         .value()
-        .attr("disabled")
-        .is_none());
+        .attr("src")
+        .unwrap()
+        .to_string();
+
+    assert!(src.starts_with("/assets/helpers.js?v="));
+
+    let request = Request::get(src).body(Body::empty()).unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        parse_response_body(response).await,
+        std::fs::read_to_string("assets/helpers.js").unwrap()
+    );
 }
 
 #[tokio::test]
-async fn test_create_todo() {
+async fn test_lock_timeout() {
     // Arrange
     let shared_state = SharedState::default();
-    let local_state = shared_state.clone();
+    shared_state.write().unwrap().lock_timeout = Duration::from_millis(50);
 
-    let app = app(shared_state);
-    let request = Request::post("/todo")
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .body(Body::from("text=a"))
-        .unwrap();
+    let app = app(shared_state.clone());
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let holder = {
+        let shared_state = shared_state.clone();
+
+        thread::spawn(move || {
+            let _state = shared_state.write().unwrap();
+            locked_tx.send(()).unwrap();
+            release_rx.recv().unwrap();
+        })
+    };
+
+    locked_rx.recv().unwrap();
+
+    let request = || Request::get("/todo").body(Body::empty()).unwrap();
 
     // Act
-    let response = app.oneshot(request).await.unwrap();
+    let blocked = app.clone().oneshot(request()).await.unwrap();
+
+    release_tx.send(()).unwrap();
+    holder.join().unwrap();
+
+    let released = app.oneshot(request()).await.unwrap();
 
     // Assert
-    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(blocked.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(released.status(), StatusCode::OK);
+}
 
-    assert_eq!(
-        local_state.read().unwrap().toggle_action,
-        TodoToggleAction::Check
-    );
+#[tokio::test]
+async fn test_write_rate_limit() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().write_limiter =
+        Some(RateLimiter::new(2, Duration::from_secs(60)));
 
-    let body = parse_response_body(response).await;
-    let document = Html::parse_document(&body);
+    let create = |text: &str| {
+        Request::post("/todo")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(format!("text={text}")))
+            .unwrap()
+    };
 
-    let counter_selector = Selector::parse(".todo-counter").unwrap();
-    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
-    let item_selector = Selector::parse(".todo-item p").unwrap();
+    for text in ["a", "b"] {
+        let response = app(shared_state.clone())
+            .oneshot(create(text))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
 
-    assert_eq!(document.select(&counter_selector).count(), 3);
-    assert_eq!(document.select(&toggle_selector).count(), 1);
-    assert_eq!(document.select(&item_selector).count(), 1);
+    // Act
+    let limited = app(shared_state.clone())
+        .oneshot(create("c"))
+        .await
+        .unwrap();
+    let read = app(shared_state.clone())
+        .oneshot(Request::get("/todo").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
 
-    assert_eq!(
-        document.select(&item_selector).next().unwrap().inner_html(),
-        "a"
-    );
-    assert!(document
-        .select(&toggle_selector)
-        .next()
-        .unwrap()
-        .value()
-        .attr("disabled")
-        .is_none());
+    // Assert
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(read.status(), StatusCode::OK);
+    assert_eq!(shared_state.read().unwrap().todo_repo.num_all_items, 2);
 }
 
 #[tokio::test]
-async fn test_toggle_completed_todo() {
+async fn test_create_todo_at_capacity() {
     // Arrange
     let shared_state = SharedState::default();
     let local_state = shared_state.clone();
@@ -166,183 +262,402 @@ async fn test_toggle_completed_todo() {
     {
         let todo_repo = &mut shared_state.write().unwrap().todo_repo;
 
-        todo_repo.create("a");
-        todo_repo.create("b");
-        todo_repo.create("c");
+        *todo_repo = TodoRepo::with_capacity(1);
+        todo_repo.create("a").unwrap();
     }
 
     let app = app(shared_state);
-    let request = Request::patch("/todo?action=Check")
+    let request = Request::post("/todo")
         .header("Content-Type", "application/x-www-form-urlencoded")
-        .body(Body::empty())
+        .body(Body::from("text=b"))
         .unwrap();
 
     // Act
     let response = app.oneshot(request).await.unwrap();
 
     // Assert
-    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.status(), StatusCode::INSUFFICIENT_STORAGE);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 1);
+}
 
-    assert_eq!(
-        local_state.read().unwrap().toggle_action,
-        TodoToggleAction::Uncheck
-    );
+#[tokio::test]
+async fn test_create_duplicate_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
 
-    let body = parse_response_body(response).await;
-    let document = Html::parse_document(&body);
+    shared_state.write().unwrap().todo_repo.create("a").unwrap();
 
-    let counter_selector = Selector::parse(".todo-counter").unwrap();
-    let delete_selector = Selector::parse("#todo-delete-completed").unwrap();
-    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
-    let item_selector = Selector::parse(".todo-item input").unwrap();
+    let request = |uri: &str| {
+        Request::post(uri)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from("text=a"))
+            .unwrap()
+    };
 
-    assert_eq!(document.select(&counter_selector).count(), 3);
-    assert_eq!(document.select(&delete_selector).count(), 1);
-    assert_eq!(document.select(&toggle_selector).count(), 1);
-    assert_eq!(document.select(&item_selector).count(), 3);
+    // Act
+    let rejected = app(shared_state.clone())
+        .oneshot(request("/todo"))
+        .await
+        .unwrap();
+    let allowed = app(shared_state)
+        .oneshot(request("/todo?allow_duplicates=true"))
+        .await
+        .unwrap();
 
-    assert_eq!(
-        document
-            .select(&item_selector)
-            .filter(|e| e.value().attr("checked").is_some())
-            .count(),
-        3
-    );
-    assert!(document
-        .select(&toggle_selector)
-        .next()
-        .unwrap()
-        .value()
-        .attr("disabled")
-        .is_none());
-    assert!(document
-        .select(&delete_selector)
-        .next()
-        .unwrap()
-        .value()
-        .attr("disabled")
-        .is_none());
+    // Assert
+    assert_eq!(rejected.status(), StatusCode::CONFLICT);
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 2);
 }
 
 #[tokio::test]
-async fn test_delete_completed_todo() {
+async fn test_create_todo_with_idempotency_key() {
     // Arrange
     let shared_state = SharedState::default();
     let local_state = shared_state.clone();
 
-    {
-        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+    let request = |key: &str| {
+        Request::post("/todo?allow_duplicates=true")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Idempotency-Key", key)
+            .body(Body::from("text=a"))
+            .unwrap()
+    };
 
-        todo_repo.create("a");
-        todo_repo.create("b");
-        todo_repo.toggle_completed(&TodoToggleAction::Check);
-        todo_repo.create("c");
-    }
+    // Act
+    let first = app(shared_state.clone())
+        .oneshot(request("retry"))
+        .await
+        .unwrap();
+    let retried = app(shared_state.clone())
+        .oneshot(request("retry"))
+        .await
+        .unwrap();
 
-    let app = app(shared_state);
-    let request = Request::delete("/todo").body(Body::empty()).unwrap();
+    // Assert
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(retried.status(), StatusCode::OK);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 1);
+
+    let id = local_state
+        .read()
+        .unwrap()
+        .todo_repo
+        .list(&TodoListFilter::All)[0]
+        .id;
+
+    assert!(parse_response_body(retried)
+        .await
+        .contains(&format!("todo-done-{}", id)));
 
     // Act
-    let response = app.oneshot(request).await.unwrap();
+    let other = app(shared_state).oneshot(request("other")).await.unwrap();
 
     // Assert
-    assert_eq!(response.status(), StatusCode::OK);
-
-    assert_eq!(
-        local_state.read().unwrap().toggle_action,
-        TodoToggleAction::Check
-    );
+    assert_eq!(other.status(), StatusCode::OK);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 2);
+}
 
-    let body = parse_response_body(response).await;
-    let document = Html::parse_document(&body);
+#[tokio::test]
+async fn test_idempotency_key_per_client() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
 
-    let counter_selector = Selector::parse(".todo-counter").unwrap();
-    let delete_selector = Selector::parse("#todo-delete-completed").unwrap();
-    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
-    let item_selector = Selector::parse(".todo-item p").unwrap();
+    let request = |peer: [u8; 4]| {
+        let mut request = Request::post("/todo?allow_duplicates=true")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Idempotency-Key", "shared")
+            .body(Body::from("text=a"))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(std::net::SocketAddr::from((peer, 4000))));
+        request
+    };
 
-    assert_eq!(document.select(&counter_selector).count(), 3);
-    assert_eq!(document.select(&delete_selector).count(), 1);
-    assert_eq!(document.select(&toggle_selector).count(), 1);
-    assert_eq!(document.select(&item_selector).count(), 1);
+    // Act
+    let first = app(shared_state.clone())
+        .oneshot(request([10, 0, 0, 1]))
+        .await
+        .unwrap();
+    let other = app(shared_state.clone())
+        .oneshot(request([10, 0, 0, 2]))
+        .await
+        .unwrap();
+    let retried = app(shared_state)
+        .oneshot(request([10, 0, 0, 1]))
+        .await
+        .unwrap();
 
-    assert_eq!(
-        document.select(&item_selector).next().unwrap().inner_html(),
-        "c"
-    );
-    assert!(document
-        .select(&toggle_selector)
-        .next()
-        .unwrap()
-        .value()
-        .attr("disabled")
-        .is_none());
-    assert!(document
-        .select(&delete_selector)
-        .next()
-        .unwrap()
-        .value()
-        .attr("disabled")
-        .is_some());
+    // Assert
+    assert_eq!(first.status(), StatusCode::OK);
+    assert_eq!(other.status(), StatusCode::OK);
+    assert_eq!(retried.status(), StatusCode::OK);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 2);
 }
 
 #[tokio::test]
-async fn test_edit_todo() {
+async fn test_base_path() {
     // Arrange
     let shared_state = SharedState::default();
-    let id;
 
     {
-        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
-        let todo = todo_repo.create("a");
+        let mut state = shared_state.write().unwrap();
 
-        id = todo.id;
+        state.base_path = String::from("/app");
+        state.todo_repo.create("a").unwrap();
     }
 
-    let app = app(shared_state);
-    let request = Request::get(format!("/todo/{id}"))
-        .body(Body::empty())
-        .unwrap();
+    let index_request = Request::get("/app").body(Body::empty()).unwrap();
+    let list_request = Request::get("/app/todo").body(Body::empty()).unwrap();
+    let unprefixed_request = Request::get("/todo").body(Body::empty()).unwrap();
 
     // Act
-    let response = app.oneshot(request).await.unwrap();
+    let index_response = app(shared_state.clone())
+        .oneshot(index_request)
+        .await
+        .unwrap();
+    let list_response = app(shared_state.clone())
+        .oneshot(list_request)
+        .await
+        .unwrap();
+    let unprefixed_response = app(shared_state).oneshot(unprefixed_request).await.unwrap();
 
     // Assert
-    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(index_response.status(), StatusCode::OK);
+    assert_eq!(list_response.status(), StatusCode::OK);
+    assert_eq!(unprefixed_response.status(), StatusCode::NOT_FOUND);
 
-    let body = parse_response_body(response).await;
+    let body = parse_response_body(index_response).await;
     let document = Html::parse_document(&body);
-    let edit_selector = Selector::parse(".todo-edit p input").unwrap();
+    let create_selector = Selector::parse("[hx-post]").unwrap();
 
-    assert_eq!(document.select(&edit_selector).count(), 1);
     assert_eq!(
         document
-            .select(&edit_selector)
+            .select(&create_selector)
             .next()
             .unwrap()
             .value()
-            .attr("value"),
-        Some("a")
+            .attr("hx-post"),
+        Some("/app/todo")
     );
+
+    let body = parse_response_body(list_response).await;
+    let document = Html::parse_document(&body);
+    let patch_selector = Selector::parse("[hx-patch]").unwrap();
+    let patch_urls = document
+        .select(&patch_selector)
+        .filter_map(|element| element.value().attr("hx-patch"))
+        .collect::<Vec<_>>();
+
+    assert!(!patch_urls.is_empty());
+    assert!(patch_urls.iter().all(|url| url.starts_with("/app/todo")));
 }
 
 #[tokio::test]
-async fn test_update_todo() {
+async fn test_openapi() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get("/openapi.json").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let spec = serde_json::from_str::<serde_json::Value>(&body).unwrap();
+
+    for (path, methods) in [
+        ("/todo", ["get", "post", "patch", "delete"].as_slice()),
+        ("/todo/{id}", ["get", "patch", "delete"].as_slice()),
+    ] {
+        for method in methods {
+            assert!(spec["paths"][path][method].is_object(), "{method} {path}");
+        }
+    }
+
+    assert!(spec["components"]["schemas"]["Todo"].is_object());
+}
+
+#[tokio::test]
+async fn test_health() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get("/health").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(parse_response_body(response).await, "ok");
+}
+
+#[tokio::test]
+async fn test_ready() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get("/ready").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_ready_poisoned_state() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let poisoned_state = shared_state.clone();
+
+    let _ = std::thread::spawn(move || {
+        let _guard = poisoned_state.write().unwrap();
+        panic!("poison the state lock");
+    })
+    .join();
+
+    let app = app(shared_state);
+    let request = Request::get("/ready").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_poisoned_state_still_serves_requests() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let poisoned_state = shared_state.clone();
+
+    let _ = std::thread::spawn(move || {
+        let mut state = poisoned_state.write().unwrap();
+        state.todo_repo.create("a").unwrap();
+        panic!("poison the state lock");
+    })
+    .join();
+
+    let create_request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=b"))
+        .unwrap();
+    let list_request = Request::get("/todo?filter=all")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let create_response = app(shared_state.clone())
+        .oneshot(create_request)
+        .await
+        .unwrap();
+    let list_response = app(shared_state).oneshot(list_request).await.unwrap();
+
+    // Assert
+    assert_eq!(create_response.status(), StatusCode::OK);
+    assert_eq!(list_response.status(), StatusCode::OK);
+
+    let body = parse_response_body(list_response).await;
+    let document = Html::parse_document(&body);
+    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+
+    assert_eq!(document.select(&list_selector).count(), 2);
+}
+
+#[tokio::test]
+async fn test_metrics_velocity() {
     // Arrange
     let shared_state = SharedState::default();
-    let id;
 
     {
         let todo_repo = &mut shared_state.write().unwrap().todo_repo;
-        let todo = todo_repo.create("a");
+        let todo = todo_repo.create("a").unwrap();
 
-        id = todo.id;
+        todo_repo.create("b").unwrap();
+        todo_repo
+            .update(
+                &todo.id,
+                TodoUpdate {
+                    is_completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
     }
 
     let app = app(shared_state);
-    let request = Request::patch(format!("/todo/{id}"))
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .body(Body::from("text=b&is_completed=true"))
+    let request = Request::get("/metrics").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let velocity = 1.0 / 24.0;
+
+    assert!(body
+        .lines()
+        .any(|line| line == format!("todomvc_completion_velocity {}", velocity)));
+}
+
+#[tokio::test]
+async fn test_metrics_counters() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let app = app(shared_state);
+
+    for text in ["a", "b", "c"] {
+        let request = Request::post("/todo")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(format!("text={text}")))
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap();
+    }
+
+    let request = Request::patch("/todo?action=Check")
+        .body(Body::empty())
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap();
+
+    let request = Request::delete("/todo").body(Body::empty()).unwrap();
+    app.clone().oneshot(request).await.unwrap();
+
+    let request = Request::get("/metrics").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    let body = parse_response_body(response).await;
+    let lines = body.lines().collect::<Vec<_>>();
+
+    for expected in [
+        "todomvc_todos_created_total 3",
+        "todomvc_todos_completed_total 3",
+        "todomvc_todos_deleted_total 3",
+        "todomvc_todos{state=\"active\"} 0",
+        "todomvc_todos{state=\"completed\"} 0",
+    ] {
+        assert!(lines.contains(&expected), "missing {expected:?} in {body}");
+    }
+}
+
+#[tokio::test]
+async fn test_list_todo_empty() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=All")
+        .body(Body::empty())
         .unwrap();
 
     // Act
@@ -351,45 +666,67 @@ async fn test_update_todo() {
     // Assert
     assert_eq!(response.status(), StatusCode::OK);
 
+    assert!(selected_filter_cookie(&response).ends_with("all"));
+
     let body = parse_response_body(response).await;
     let document = Html::parse_document(&body);
 
-    let item_input_selector = Selector::parse(".todo-item input").unwrap();
     let counter_selector = Selector::parse(".todo-counter").unwrap();
-    let delete_selector = Selector::parse("#todo-delete-completed").unwrap();
     let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
-    let item_p_selector = Selector::parse(".todo-item p s").unwrap();
+    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
 
     assert_eq!(document.select(&counter_selector).count(), 3);
-    assert_eq!(document.select(&delete_selector).count(), 1);
     assert_eq!(document.select(&toggle_selector).count(), 1);
-    assert_eq!(document.select(&item_p_selector).count(), 1);
+    assert_eq!(document.select(&list_selector).count(), 0);
 
-    assert_eq!(
-        document
-            .select(&item_p_selector)
-            .next()
-            .unwrap()
-            .inner_html(),
-        "b"
-    );
-    assert_eq!(
-        document
-            .select(&toggle_selector)
-            .next()
-            .unwrap()
-            .inner_html(),
-        "Uncheck all"
-    );
     assert!(document
-        .select(&item_input_selector)
+        .select(&toggle_selector)
         .next()
         .unwrap()
         .value()
-        .attr("checked")
+        .attr("disabled")
         .is_some());
+}
+
+#[tokio::test]
+async fn test_list_todo_non_empty() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        todo_repo.create("a").unwrap();
+        todo_repo.create("b").unwrap();
+        todo_repo.create("c").unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=Active")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert!(selected_filter_cookie(&response).ends_with("active"));
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+
+    let counter_selector = Selector::parse(".todo-counter").unwrap();
+    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+
+    assert_eq!(document.select(&counter_selector).count(), 3);
+    assert_eq!(document.select(&toggle_selector).count(), 1);
+    assert_eq!(document.select(&list_selector).count(), 3);
+
     assert!(document
-        .select(&delete_selector)
+        .select(&toggle_selector)
         .next()
         .unwrap()
         .value()
@@ -398,20 +735,27 @@ async fn test_update_todo() {
 }
 
 #[tokio::test]
-async fn test_delete_todo() {
+async fn test_list_todo_selected_filter_cookie() {
     // Arrange
     let shared_state = SharedState::default();
-    let id;
+    let key = shared_state.read().unwrap().cookie_key.clone();
 
     {
         let todo_repo = &mut shared_state.write().unwrap().todo_repo;
-        let todo = todo_repo.create("a");
+        let todo = todo_repo.create("a").unwrap();
 
-        id = todo.id;
+        todo_repo.create("b").unwrap();
+        todo_repo.set_completed(&todo.id, true).unwrap();
     }
 
+    let mut jar = CookieJar::new();
+    jar.signed_mut(&key.0)
+        .add(Cookie::new("todo_filter", "completed"));
+    let cookie = jar.get("todo_filter").unwrap();
+
     let app = app(shared_state);
-    let request = Request::delete(format!("/todo/{id}"))
+    let request = Request::get("/todo")
+        .header("Cookie", cookie.stripped().to_string())
         .body(Body::empty())
         .unwrap();
 
@@ -420,40 +764,4216 @@ async fn test_delete_todo() {
 
     // Assert
     assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("X-Selected-Filter").unwrap(),
+        "completed"
+    );
+    assert!(response.headers().get("Set-Cookie").is_none());
 
     let body = parse_response_body(response).await;
     let document = Html::parse_document(&body);
+    let item_selector = Selector::parse(".todo-item p s").unwrap();
 
-    let counter_selector = Selector::parse(".todo-counter").unwrap();
-    let delete_selector = Selector::parse("#todo-delete-completed").unwrap();
-    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
-    let item_selector = Selector::parse(".todo-item").unwrap();
+    assert_eq!(document.select(&item_selector).count(), 1);
+    assert_eq!(
+        document.select(&item_selector).next().unwrap().inner_html(),
+        "a"
+    );
+}
 
-    assert_eq!(document.select(&counter_selector).count(), 3);
-    assert_eq!(document.select(&delete_selector).count(), 1);
-    assert_eq!(document.select(&toggle_selector).count(), 1);
-    assert_eq!(document.select(&item_selector).count(), 0);
+#[tokio::test]
+async fn test_list_todo_tampered_filter_cookie() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get("/todo")
+        .header("Cookie", "todo_filter=completed")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
 
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("X-Selected-Filter").unwrap(), "all");
+}
+
+#[tokio::test]
+async fn test_list_todo_selected_filter_header() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get("/todo?filter=Active")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
-        document
-            .select(&toggle_selector)
-            .next()
-            .unwrap()
-            .inner_html(),
-        "Check all"
+        response.headers().get("X-Selected-Filter").unwrap(),
+        "active"
     );
-    assert!(document
-        .select(&delete_selector)
+}
+
+#[tokio::test]
+async fn test_list_todo_display_filter() {
+    // Arrange
+    let app = app(SharedState::default());
+    let uri = format!("/todo?filter={}", TodoListFilter::Completed);
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("X-Selected-Filter").unwrap(),
+        "completed"
+    );
+}
+
+#[tokio::test]
+async fn test_toggle_completed_button_round_trip() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let request = Request::get("/todo").body(Body::empty()).unwrap();
+    let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+    let uri = document
+        .select(&toggle_selector)
         .next()
         .unwrap()
         .value()
-        .attr("disabled")
+        .attr("hx-patch")
+        .unwrap()
+        .to_string();
+
+    let request = Request::patch(uri).body(Body::empty()).unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_completed_items, 1);
+}
+
+#[tokio::test]
+async fn test_reset_toggle_button() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let button_action = |body: String| {
+        let document = Html::parse_document(&body);
+        let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+        document
+            .select(&toggle_selector)
+            .next()
+            .unwrap()
+            .value()
+            .attr("hx-patch")
+            .unwrap()
+            .to_string()
+    };
+
+    let request = Request::get("/todo").body(Body::empty()).unwrap();
+    let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+    let stale_action = button_action(parse_response_body(response).await);
+
+    // Completed behind the page's back, so its button still offers to check everything.
+    shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .set_completed(&id, true)
+        .unwrap();
+
+    let request = Request::post("/todo/toggle/reset")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(stale_action, "/todo?action=Check");
+    assert_eq!(
+        button_action(parse_response_body(response).await),
+        "/todo?action=Uncheck"
+    );
+}
+
+#[tokio::test]
+async fn test_list_todo_selected_filter_header_default() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get("/todo").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("X-Selected-Filter").unwrap(), "all");
+}
+
+#[tokio::test]
+async fn test_list_todo_by_tag() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let app = app(shared_state);
+
+    for body in ["text=a&tags=Work,home", "text=b&tags=home", "text=c"] {
+        let request = Request::post("/todo")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .unwrap();
+
+        app.clone().oneshot(request).await.unwrap();
+    }
+
+    let request = Request::get("/todo?filter=All&tag=work")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+
+    let item_selector = Selector::parse(".todo-item p").unwrap();
+    let tag_selector = Selector::parse(".todo-item .todo-tag").unwrap();
+
+    assert_eq!(document.select(&item_selector).count(), 1);
+    assert_eq!(
+        document.select(&item_selector).next().unwrap().inner_html(),
+        "a"
+    );
+    assert_eq!(
+        document
+            .select(&tag_selector)
+            .map(|e| e.inner_html())
+            .collect::<Vec<_>>(),
+        vec!["work", "home"]
+    );
+}
+
+#[tokio::test]
+async fn test_list_todo_completion_duration() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let mut completed = Todo::new("a");
+    let active = Todo::new("b");
+
+    completed.status = TodoStatus::Done;
+    completed.completed_at = Some(completed.created_at + Duration::from_secs(2 * 60 * 60));
+
+    shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .import(vec![completed, active]);
+
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=All")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let duration_selector = Selector::parse(".todo-item .todo-duration").unwrap();
+
+    assert_eq!(document.select(&duration_selector).count(), 1);
+    assert_eq!(
+        document
+            .select(&duration_selector)
+            .next()
+            .unwrap()
+            .inner_html(),
+        "done in 2h"
+    );
+}
+
+#[tokio::test]
+async fn test_list_todo_created_ago() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let mut old = Todo::new("a");
+
+    old.created_at -= Duration::from_secs(3 * 60 * 60);
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        todo_repo.import(vec![old]);
+        todo_repo.create("b").unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=All")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let created_selector = Selector::parse(".todo-item small.todo-created").unwrap();
+
+    assert_eq!(
+        document
+            .select(&created_selector)
+            .map(|element| element.inner_html())
+            .collect::<Vec<_>>(),
+        vec!["just now", "3h ago"]
+    );
+}
+
+#[tokio::test]
+async fn test_count_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.create("b").unwrap();
+        todo_repo.create("c").unwrap();
+        todo_repo
+            .update(
+                &todo.id,
+                TodoUpdate {
+                    is_completed: Some(true),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo/count").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let counts: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(counts["completed"], 1);
+    assert_eq!(counts["active"], 2);
+    assert_eq!(counts["all"], 3);
+    assert_eq!(counts["completion_pct"], 33);
+}
+
+#[tokio::test]
+async fn test_grouped_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.create("b").unwrap();
+        todo_repo.create("c").unwrap();
+        todo_repo.set_completed(&todo.id, true).unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo/grouped").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let grouped: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let texts = |group: &str| {
+        grouped[group]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|todo| todo["text"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(texts("active"), ["c", "b"]);
+    assert_eq!(texts("completed"), ["a"]);
+    assert_eq!(grouped["counts"]["active"], 2);
+    assert_eq!(grouped["counts"]["completed"], 1);
+    assert_eq!(grouped["counts"]["all"], 3);
+}
+
+#[tokio::test]
+async fn test_todo_summary() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.create("b").unwrap();
+        todo_repo.set_completed(&todo.id, true).unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo/summary").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response
+        .headers()
+        .get("Content-Type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    assert_eq!(
+        parse_response_body(response).await,
+        "1 active, 1 done (2 total)"
+    );
+}
+
+#[tokio::test]
+async fn test_export_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    {
+        let state = &mut shared_state.write().unwrap();
+        let todo = state.todo_repo.create("a").unwrap();
+
+        state.todo_repo.create("b").unwrap();
+        state.todo_repo.set_completed(&todo.id, true).unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo/export").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "application/json"
+    );
+
+    let body = parse_response_body(response).await;
+    let exported: Vec<Todo> = serde_json::from_str(&body).unwrap();
+    let document: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(
+        exported,
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .list(&TodoListFilter::All)
+    );
+    assert!(document[0]["created_at"].as_str().unwrap().ends_with('Z'));
+    assert!(document[0]["completed_at"].is_null());
+    assert!(document[1]["completed_at"].as_str().unwrap().ends_with('Z'));
+}
+
+#[tokio::test]
+async fn test_import_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let existing = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+    let new = Todo::new("b");
+
+    let app = app(shared_state);
+    let request = Request::post("/todo/import")
+        .header("Content-Type", "application/json")
+        .body(Body::from(
+            serde_json::to_string(&[existing, new.clone()]).unwrap(),
+        ))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(result["imported"], 1);
+    assert_eq!(result["skipped"], 1);
+
+    let state = local_state.read().unwrap();
+
+    assert_eq!(state.todo_repo.get(&new.id).unwrap().text, "b");
+    assert_eq!(state.todo_repo.counters(), (0, 2, 2));
+}
+
+#[tokio::test]
+async fn test_import_legacy_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let (done, open) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+    let body = serde_json::json!([
+        {
+            "id": done,
+            "text": "a",
+            "is_completed": true,
+            "created_at": "2024-05-01T17:00:00Z",
+            "completed_at": "2024-05-02T17:00:00Z",
+        },
+        {
+            "id": open,
+            "text": "b",
+            "is_completed": false,
+            "created_at": "2024-05-01T17:00:00Z",
+        },
+    ]);
+
+    let app = app(shared_state);
+    let request = Request::post("/todo/import")
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let state = local_state.read().unwrap();
+
+    assert!(state.todo_repo.get(&done).unwrap().is_completed());
+    assert!(!state.todo_repo.get(&open).unwrap().is_completed());
+    assert_eq!(state.todo_repo.counters(), (1, 1, 2));
+}
+
+#[tokio::test]
+async fn test_create_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    let app = app(shared_state);
+    let request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=a"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .toggle_action(&TodoListFilter::All),
+        TodoToggleAction::Check
+    );
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+
+    let counter_selector = Selector::parse(".todo-counter").unwrap();
+    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+    let item_selector = Selector::parse(".todo-item p").unwrap();
+
+    assert_eq!(document.select(&counter_selector).count(), 3);
+    assert_eq!(document.select(&toggle_selector).count(), 1);
+    assert_eq!(document.select(&item_selector).count(), 1);
+
+    assert_eq!(
+        document.select(&item_selector).next().unwrap().inner_html(),
+        "a"
+    );
+    assert!(document
+        .select(&toggle_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("disabled")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_create_todo_resets_input() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=a"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["HX-Trigger"], "todo-created");
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let control_selector = Selector::parse("#todo-new-control[hx-swap-oob=true]").unwrap();
+    let input_selector = Selector::parse("#todo-new-control input[name=text]").unwrap();
+
+    assert_eq!(document.select(&control_selector).count(), 1);
+    assert!(document
+        .select(&input_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("value")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_toggle_completed_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        todo_repo.create("a").unwrap();
+        todo_repo.create("b").unwrap();
+        todo_repo.create("c").unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::patch("/todo?action=Check")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .toggle_action(&TodoListFilter::All),
+        TodoToggleAction::Uncheck
+    );
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+
+    let counter_selector = Selector::parse(".todo-counter").unwrap();
+    let delete_selector = Selector::parse("#todo-delete-completed").unwrap();
+    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+    let item_selector = Selector::parse(".todo-item input").unwrap();
+
+    assert_eq!(document.select(&counter_selector).count(), 3);
+    assert_eq!(document.select(&delete_selector).count(), 1);
+    assert_eq!(document.select(&toggle_selector).count(), 1);
+    assert_eq!(document.select(&item_selector).count(), 3);
+
+    assert_eq!(
+        document
+            .select(&item_selector)
+            .filter(|e| e.value().attr("checked").is_some())
+            .count(),
+        3
+    );
+    assert!(document
+        .select(&toggle_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("disabled")
+        .is_none());
+    assert!(document
+        .select(&delete_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("disabled")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_clear_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.create("b").unwrap();
+        todo_repo.set_completed(&todo.id, true).unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::delete("/todo/all?confirm=true")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let state = local_state.read().unwrap();
+
+    assert_eq!(state.todo_repo.num_all_items, 0);
+    assert_eq!(
+        state.todo_repo.toggle_action(&TodoListFilter::All),
+        TodoToggleAction::Check
+    );
+}
+
+#[tokio::test]
+async fn test_clear_todos_without_confirm() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let app = app(shared_state);
+    let request = Request::delete("/todo/all").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 1);
+}
+
+#[tokio::test]
+async fn test_list_paths_share_order() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    let created_at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+    // Pairs of todos share a timestamp, so only their creation order tells them apart.
+    let todos = ["a", "b", "c", "d", "e"]
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| Todo {
+            created_at: created_at + Duration::from_secs(i as u64 / 2),
+            ..Todo::new(text)
+        })
+        .collect();
+
+    shared_state.write().unwrap().todo_repo.import(todos);
+
+    let requests = [
+        Request::get("/todo"),
+        Request::patch("/todo?action=Uncheck"),
+        Request::delete("/todo"),
+    ];
+    let mut orders = Vec::new();
+
+    for request in requests {
+        let request = request.body(Body::empty()).unwrap();
+
+        // Act
+        let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = parse_response_body(response).await;
+        let document = Html::parse_document(&body);
+        let text_selector = Selector::parse(".todo-item p.is-flex-grow-1").unwrap();
+
+        orders.push(
+            document
+                .select(&text_selector)
+                .map(|text| text.text().collect::<String>().trim().to_string())
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    assert_eq!(orders[0], ["e", "d", "c", "b", "a"]);
+    assert_eq!(orders[1], orders[0]);
+    assert_eq!(orders[2], orders[0]);
+}
+
+#[tokio::test]
+async fn test_delete_completed_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        todo_repo.create("a").unwrap();
+        todo_repo.create("b").unwrap();
+        todo_repo.toggle_completed(&TodoToggleAction::Check, &TodoListFilter::All);
+        todo_repo.create("c").unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::delete("/todo").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .toggle_action(&TodoListFilter::All),
+        TodoToggleAction::Check
+    );
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+
+    let counter_selector = Selector::parse(".todo-counter").unwrap();
+    let delete_selector = Selector::parse("#todo-delete-completed").unwrap();
+    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+    let item_selector = Selector::parse(".todo-item p").unwrap();
+
+    assert_eq!(document.select(&counter_selector).count(), 3);
+    assert_eq!(document.select(&delete_selector).count(), 1);
+    assert_eq!(document.select(&toggle_selector).count(), 1);
+    assert_eq!(document.select(&item_selector).count(), 1);
+
+    assert_eq!(
+        document.select(&item_selector).next().unwrap().inner_html(),
+        "c"
+    );
+    assert!(document
+        .select(&toggle_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("disabled")
+        .is_none());
+    assert!(document
+        .select(&delete_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("disabled")
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_edit_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        id = todo.id;
+    }
+
+    let app = app(shared_state);
+    let request = Request::get(format!("/todo/{id}"))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let edit_selector = Selector::parse(".todo-edit p input").unwrap();
+
+    assert_eq!(document.select(&edit_selector).count(), 1);
+    assert_eq!(
+        document
+            .select(&edit_selector)
+            .next()
+            .unwrap()
+            .value()
+            .attr("value"),
+        Some("a")
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_todo_id() {
+    for request in [
+        Request::get("/todo/not-a-uuid")
+            .body(Body::empty())
+            .unwrap(),
+        Request::patch("/todo/not-a-uuid")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from("text=a"))
+            .unwrap(),
+        Request::delete("/todo/not-a-uuid")
+            .body(Body::empty())
+            .unwrap(),
+    ] {
+        // Arrange
+        let app = app(SharedState::default());
+
+        // Act
+        let response = app.oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = parse_response_body(response).await;
+        let document = Html::parse_document(&body);
+        let notification_selector = Selector::parse(".notification.todo-bad-request").unwrap();
+
+        assert_eq!(
+            document
+                .select(&notification_selector)
+                .next()
+                .unwrap()
+                .inner_html()
+                .trim(),
+            "Todo ids must be UUIDs"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_view_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let app = app(shared_state);
+    let request = Request::get(format!("/todo/{id}/view"))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let item_selector = Selector::parse(".todo-item p").unwrap();
+    let edit_selector = Selector::parse(".todo-edit").unwrap();
+
+    assert_eq!(document.select(&item_selector).count(), 1);
+    assert_eq!(
+        document.select(&item_selector).next().unwrap().inner_html(),
+        "a"
+    );
+    assert_eq!(document.select(&edit_selector).count(), 0);
+}
+
+#[tokio::test]
+async fn test_view_todo_not_found() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get(format!("/todo/{}/view", uuid::Uuid::new_v4()))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_get_todo_not_found() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get(format!("/todo/{}", uuid::Uuid::new_v4()))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let notification_selector = Selector::parse(".notification.todo-not-found").unwrap();
+
+    assert_eq!(document.select(&notification_selector).count(), 1);
+    assert_eq!(
+        document
+            .select(&notification_selector)
+            .next()
+            .unwrap()
+            .inner_html()
+            .trim(),
+        "Todo not found"
+    );
+}
+
+#[tokio::test]
+async fn test_update_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        id = todo.id;
+    }
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=b&is_completed=true"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+
+    let item_input_selector = Selector::parse(".todo-item input").unwrap();
+    let counter_selector = Selector::parse(".todo-counter").unwrap();
+    let delete_selector = Selector::parse("#todo-delete-completed").unwrap();
+    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+    let item_p_selector = Selector::parse(".todo-item p s").unwrap();
+
+    assert_eq!(document.select(&counter_selector).count(), 3);
+    assert_eq!(document.select(&delete_selector).count(), 1);
+    assert_eq!(document.select(&toggle_selector).count(), 1);
+    assert_eq!(document.select(&item_p_selector).count(), 1);
+
+    assert_eq!(
+        document
+            .select(&item_p_selector)
+            .next()
+            .unwrap()
+            .inner_html(),
+        "b"
+    );
+    assert_eq!(
+        document
+            .select(&toggle_selector)
+            .next()
+            .unwrap()
+            .inner_html(),
+        "Uncheck all"
+    );
+    assert!(document
+        .select(&item_input_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("checked")
+        .is_some());
+    assert!(document
+        .select(&delete_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("disabled")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_update_todo_blank_text() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{}", todo.id))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=+++&description=b"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let rejected_selector = Selector::parse(".todo-item.todo-rejected p").unwrap();
+
+    assert_eq!(
+        document
+            .select(&rejected_selector)
+            .next()
+            .unwrap()
+            .inner_html(),
+        "a"
+    );
+    assert_eq!(
+        local_state.read().unwrap().todo_repo.get(&todo.id),
+        Ok(todo)
+    );
+}
+
+#[tokio::test]
+async fn test_update_todo_description() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("description=more+details"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let description_selector = Selector::parse(".todo-item .todo-description .content").unwrap();
+
+    assert_eq!(
+        document
+            .select(&description_selector)
+            .next()
+            .unwrap()
+            .inner_html(),
+        "more details"
+    );
+
+    let todo = local_state.read().unwrap().todo_repo.get(&id).unwrap();
+
+    assert_eq!(todo.text, "a");
+    assert_eq!(todo.description.as_deref(), Some("more details"));
+}
+
+#[tokio::test]
+async fn test_update_todo_clears_recurrence() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let update = |body: &'static str| {
+        app(shared_state.clone()).oneshot(
+            Request::patch(format!("/todo/{id}"))
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+    };
+    let recurrence = || {
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .get(&id)
+            .unwrap()
+            .recurrence
+    };
+
+    // Act
+    let set = update("recurrence=weekly").await.unwrap();
+    let recurrence_set = recurrence();
+    let kept = update("text=b").await.unwrap();
+    let recurrence_kept = recurrence();
+    let cleared = update("recurrence=").await.unwrap();
+
+    // Assert
+    assert_eq!(set.status(), StatusCode::OK);
+    assert_eq!(kept.status(), StatusCode::OK);
+    assert_eq!(cleared.status(), StatusCode::OK);
+    assert_eq!(recurrence_set, Some(Recurrence::Weekly));
+    assert_eq!(recurrence_kept, Some(Recurrence::Weekly));
+    assert_eq!(recurrence(), None);
+}
+
+#[tokio::test]
+async fn test_update_todo_clears_due_at() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let update = |body: &'static str| {
+        app(shared_state.clone()).oneshot(
+            Request::patch(format!("/todo/{id}"))
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(body))
+                .unwrap(),
+        )
+    };
+    let due_at = || {
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .get(&id)
+            .unwrap()
+            .due_at
+    };
+
+    // Act
+    let set = update("due_at=2024-05-01T17:00:00Z").await.unwrap();
+    let due_at_set = due_at();
+    let kept = update("text=b").await.unwrap();
+    let due_at_kept = due_at();
+    let cleared = update("due_at=").await.unwrap();
+
+    // Assert
+    assert_eq!(set.status(), StatusCode::OK);
+    assert_eq!(kept.status(), StatusCode::OK);
+    assert_eq!(cleared.status(), StatusCode::OK);
+    assert_eq!(
+        due_at_set,
+        Some(humantime::parse_rfc3339("2024-05-01T17:00:00Z").unwrap())
+    );
+    assert_eq!(due_at_kept, due_at_set);
+    assert_eq!(due_at(), None);
+}
+
+#[tokio::test]
+async fn test_update_todo_description_too_long() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from(format!(
+            "description={}",
+            "a".repeat(MAX_DESCRIPTION_LEN + 1)
+        )))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .get(&id)
+            .unwrap()
+            .description,
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_subtask_routes() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let request = Request::post(format!("/todo/{id}/subtask"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=step"))
+        .unwrap();
+    let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let subtask_selector = Selector::parse(".todo-item .todo-subtask input").unwrap();
+    let uri = document
+        .select(&subtask_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("hx-patch")
+        .unwrap()
+        .to_string();
+
+    let request = Request::patch(uri).body(Body::empty()).unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let state = local_state.read().unwrap();
+    let todo = state.todo_repo.get(&id).unwrap();
+
+    assert!(todo.subtasks[0].done);
+    assert!(todo.is_completed());
+    assert_eq!(state.todo_repo.num_completed_items, 1);
+}
+
+#[tokio::test]
+async fn test_update_todo_concurrently() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        id = todo.id;
+    }
+
+    let app = app(shared_state);
+    let text_request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=b"))
+        .unwrap();
+    let completed_request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("is_completed=true"))
+        .unwrap();
+
+    // Act
+    let (text_response, completed_response) = tokio::join!(
+        tokio::spawn(app.clone().oneshot(text_request)),
+        tokio::spawn(app.oneshot(completed_request)),
+    );
+
+    // Assert
+    assert_eq!(text_response.unwrap().unwrap().status(), StatusCode::OK);
+    assert_eq!(
+        completed_response.unwrap().unwrap().status(),
+        StatusCode::OK
+    );
+
+    let state = local_state.read().unwrap();
+    let todo = state.todo_repo.get(&id).unwrap();
+
+    assert_eq!(todo.text, "b");
+    assert!(todo.is_completed());
+
+    assert_eq!(state.todo_repo.num_completed_items, 1);
+    assert_eq!(state.todo_repo.num_active_items, 0);
+    assert_eq!(state.todo_repo.num_all_items, 1);
+}
+
+#[tokio::test]
+async fn test_update_todo_if_match() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let request = Request::get(format!("/todo/{id}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+    let etag = response.headers().get("ETag").unwrap().clone();
+
+    let request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("If-Match", etag.clone())
+        .body(Body::from("text=b"))
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers().get("ETag").unwrap(), etag);
+    assert_eq!(
+        local_state.read().unwrap().todo_repo.get(&id).unwrap().text,
+        "b"
+    );
+}
+
+#[tokio::test]
+async fn test_update_todo_stale_if_match() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+    let stale = todo.etag();
+
+    shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .update(
+            &todo.id,
+            TodoUpdate {
+                text: Some("b".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+    let request = Request::patch(format!("/todo/{}", todo.id))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("If-Match", stale)
+        .body(Body::from("text=c"))
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .get(&todo.id)
+            .unwrap()
+            .text,
+        "b"
+    );
+}
+
+#[tokio::test]
+async fn test_update_todo_without_if_match() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .set_completed(&todo.id, true)
+        .unwrap();
+
+    let request = Request::patch(format!("/todo/{}", todo.id))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=b"))
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .get(&todo.id)
+            .unwrap()
+            .text,
+        "b"
+    );
+}
+
+#[tokio::test]
+async fn test_delete_todo_stale_if_match() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+    let stale = todo.etag();
+
+    shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .set_completed(&todo.id, true)
+        .unwrap();
+
+    let request = Request::delete(format!("/todo/{}", todo.id))
+        .header("If-Match", stale)
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 1);
+}
+
+#[tokio::test]
+async fn test_complete_todo_twice() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let app = app(shared_state);
+
+    // Act
+    for _ in 0..2 {
+        let request = Request::patch(format!("/todo/{id}/complete"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    // Assert
+    let state = local_state.read().unwrap();
+
+    assert!(state.todo_repo.get(&id).unwrap().is_completed());
+    assert_eq!(state.todo_repo.counters(), (1, 0, 1));
+    assert_eq!(
+        state.todo_repo.toggle_action(&TodoListFilter::All),
+        TodoToggleAction::Uncheck
+    );
+}
+
+#[tokio::test]
+async fn test_incomplete_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        id = todo_repo.create("a").unwrap().id;
+        todo_repo.set_completed(&id, true).unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{id}/incomplete"))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let state = local_state.read().unwrap();
+
+    assert!(!state.todo_repo.get(&id).unwrap().is_completed());
+    assert_eq!(state.todo_repo.counters(), (0, 1, 1));
+}
+
+#[tokio::test]
+async fn test_move_todo_to_filter() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.create("b").unwrap();
+        id = todo.id;
+    }
+
+    let app = app(shared_state);
+    let request = Request::post(format!("/todo/{id}/move-to-filter?filter=Completed"))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let state = local_state.read().unwrap();
+
+    assert!(state.todo_repo.get(&id).unwrap().is_completed());
+
+    assert_eq!(state.todo_repo.num_completed_items, 1);
+    assert_eq!(state.todo_repo.num_active_items, 1);
+    assert_eq!(state.todo_repo.num_all_items, 2);
+}
+
+#[tokio::test]
+async fn test_move_todo_to_filter_all() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let app = app(shared_state);
+    let request = Request::post(format!("/todo/{id}/move-to-filter?filter=All"))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_title_shows_active_count() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id = {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        todo_repo.create("b").unwrap();
+        todo_repo.create("a").unwrap().id
+    };
+
+    let create_request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=c"))
+        .unwrap();
+    let delete_request = Request::delete(format!("/todo/{id}"))
+        .body(Body::empty())
+        .unwrap();
+
+    for (request, expected) in [(create_request, "(3)"), (delete_request, "(2)")] {
+        // Act
+        let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = parse_response_body(response).await;
+        let document = Html::parse_document(&body);
+        let title_selector = Selector::parse("title#todo-title[hx-swap-oob]").unwrap();
+        let title = document.select(&title_selector).next().unwrap();
+
+        assert!(title.text().collect::<String>().starts_with(expected));
+    }
+}
+
+#[tokio::test]
+async fn test_delete_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        id = todo.id;
+    }
+
+    let app = app(shared_state);
+    let request = Request::delete(format!("/todo/{id}"))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+
+    let counter_selector = Selector::parse(".todo-counter").unwrap();
+    let delete_selector = Selector::parse("#todo-delete-completed").unwrap();
+    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+    let item_selector = Selector::parse(".todo-item").unwrap();
+
+    assert_eq!(document.select(&counter_selector).count(), 3);
+    assert_eq!(document.select(&delete_selector).count(), 1);
+    assert_eq!(document.select(&toggle_selector).count(), 1);
+    assert_eq!(document.select(&item_selector).count(), 0);
+
+    assert_eq!(
+        document
+            .select(&toggle_selector)
+            .next()
+            .unwrap()
+            .inner_html(),
+        "Check all"
+    );
+    assert!(document
+        .select(&delete_selector)
+        .next()
+        .unwrap()
+        .value()
+        .attr("disabled")
         .is_some());
     assert!(document
         .select(&delete_selector)
         .next()
         .unwrap()
-        .value()
-        .attr("disabled")
-        .is_some());
+        .value()
+        .attr("disabled")
+        .is_some());
+}
+
+#[tokio::test]
+async fn test_create_todo_publishes_event() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let mut receiver = shared_state.read().unwrap().events.subscribe();
+
+    let app = app(shared_state);
+    let request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=a"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    match receiver.try_recv() {
+        Ok(TodoEvent::Created(_)) => {}
+        event => panic!("expected a created event, got {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn test_read_only_mode() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    shared_state.write().unwrap().admin_token = Some(String::from("secret"));
+
+    let app = app(shared_state);
+    let toggle_request = |enabled: bool| {
+        Request::post(format!("/admin/readonly?enabled={}", enabled))
+            .header("Authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let create_request = || {
+        Request::post("/todo")
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from("text=a"))
+            .unwrap()
+    };
+
+    // Act
+    let enabled = app.clone().oneshot(toggle_request(true)).await.unwrap();
+    let rejected = app.clone().oneshot(create_request()).await.unwrap();
+    let deduplicated = app
+        .clone()
+        .oneshot(
+            Request::post("/admin/deduplicate")
+                .header("Authorization", "Bearer secret")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let listed = app
+        .clone()
+        .oneshot(Request::get("/todo").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let disabled = app.clone().oneshot(toggle_request(false)).await.unwrap();
+    let created = app.oneshot(create_request()).await.unwrap();
+
+    // Assert
+    assert_eq!(enabled.status(), StatusCode::OK);
+
+    let body = parse_response_body(enabled).await;
+    let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(result["read_only"], true);
+    assert_eq!(rejected.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(deduplicated.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(listed.status(), StatusCode::OK);
+    assert_eq!(disabled.status(), StatusCode::OK);
+    assert_eq!(created.status(), StatusCode::OK);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 1);
+}
+
+#[tokio::test]
+async fn test_deduplicate_todos_requires_admin() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().admin_token = Some(String::from("secret"));
+
+    let app = app(shared_state);
+    let request = Request::post("/admin/deduplicate")
+        .header("Authorization", "Bearer wrong")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_deduplicate_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    {
+        let state = &mut shared_state.write().unwrap();
+
+        state.admin_token = Some(String::from("secret"));
+        state.todo_repo.create("a").unwrap();
+        state.todo_repo.create("A ").unwrap();
+        state.todo_repo.create("b").unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::post("/admin/deduplicate?keep=Oldest")
+        .header("Authorization", "Bearer secret")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let result: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(result["removed"], 1);
+    assert_eq!(local_state.read().unwrap().todo_repo.counters(), (0, 2, 2));
+}
+
+#[tokio::test]
+async fn test_apply_transaction() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let operations = serde_json::json!([
+        { "op": "create", "text": "b" },
+        { "op": "create", "text": "c" },
+        { "op": "update", "id": id, "is_completed": true },
+        { "op": "toggle", "action": "Check" },
+    ]);
+
+    let app = app(shared_state);
+    let request = Request::post("/todo/transaction")
+        .header("Content-Type", "application/json")
+        .body(Body::from(operations.to_string()))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let state = local_state.read().unwrap();
+
+    assert!(state.todo_repo.get(&id).unwrap().is_completed());
+    assert_eq!(state.todo_repo.counters(), (3, 0, 3));
+
+    let metrics = &state.metrics;
+
+    assert_eq!(metrics.todos_created.load(Ordering::Relaxed), 2);
+    assert_eq!(metrics.todos_completed.load(Ordering::Relaxed), 3);
+    assert_eq!(metrics.todos_deleted.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn test_set_completed_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let (a, b);
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        a = todo_repo.create("a").unwrap().id;
+        b = todo_repo.create("b").unwrap().id;
+        todo_repo.create("c").unwrap();
+        todo_repo.set_completed(&b, true).unwrap();
+    }
+
+    let body = serde_json::json!({ "ids": [a, b], "is_completed": true });
+
+    let app = app(shared_state);
+    let request = Request::patch("/todo/batch/complete")
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body: serde_json::Value =
+        serde_json::from_str(&parse_response_body(response).await).unwrap();
+
+    assert_eq!(body["affected"], 1);
+    assert_eq!(local_state.read().unwrap().todo_repo.counters(), (2, 1, 3));
+}
+
+#[tokio::test]
+async fn test_add_and_remove_tag_on_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let (a, b, c);
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        a = todo_repo.create("a").unwrap().id;
+        b = todo_repo.create("b").unwrap().id;
+        c = todo_repo.create("c").unwrap().id;
+    }
+
+    let app = app(shared_state);
+    let request = |path: &str, body: serde_json::Value| {
+        Request::post(path)
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let tags = |id| local_state.read().unwrap().todo_repo.get(&id).unwrap().tags;
+
+    // Act
+    let added = app
+        .clone()
+        .oneshot(request(
+            "/todo/tags/add",
+            serde_json::json!({ "ids": [a, b], "tag": " Project " }),
+        ))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(added.status(), StatusCode::OK);
+
+    let body: serde_json::Value = serde_json::from_str(&parse_response_body(added).await).unwrap();
+
+    assert_eq!(body["affected"], 2);
+    assert_eq!(tags(a), ["project"]);
+    assert_eq!(tags(b), ["project"]);
+    assert!(tags(c).is_empty());
+
+    // Act
+    let removed = app
+        .oneshot(request(
+            "/todo/tags/remove",
+            serde_json::json!({ "ids": [a], "tag": "PROJECT" }),
+        ))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(removed.status(), StatusCode::OK);
+    assert!(tags(a).is_empty());
+    assert_eq!(tags(b), ["project"]);
+    assert!(tags(c).is_empty());
+}
+
+#[tokio::test]
+async fn test_apply_transaction_rollback() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let operations = serde_json::json!([
+        { "op": "create", "text": "b" },
+        { "op": "delete", "id": uuid::Uuid::new_v4() },
+        { "op": "update", "id": id, "text": "c" },
+    ]);
+
+    let app = app(shared_state);
+    let request = Request::post("/todo/transaction")
+        .header("Content-Type", "application/json")
+        .body(Body::from(operations.to_string()))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let state = local_state.read().unwrap();
+
+    assert_eq!(state.todo_repo.get(&id).unwrap().text, "a");
+    assert_eq!(state.todo_repo.counters(), (0, 1, 1));
+    assert_eq!(state.metrics.todos_created.load(Ordering::Relaxed), 0);
+}
+
+#[tokio::test]
+async fn test_apply_transaction_rejects_invalid_text() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+    let too_long = "b".repeat(MAX_TEXT_LEN + 1);
+
+    for operations in [
+        serde_json::json!([
+            { "op": "create", "text": "b" },
+            { "op": "create", "text": too_long },
+        ]),
+        serde_json::json!([
+            { "op": "create", "text": "b" },
+            { "op": "update", "id": id, "text": too_long },
+        ]),
+        serde_json::json!([
+            { "op": "create", "text": "b" },
+            { "op": "create", "text": "  " },
+        ]),
+    ] {
+        let request = Request::post("/todo/transaction")
+            .header("Content-Type", "application/json")
+            .body(Body::from(operations.to_string()))
+            .unwrap();
+
+        // Act
+        let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{operations}");
+
+        let state = shared_state.read().unwrap();
+
+        assert_eq!(state.todo_repo.get(&id).unwrap().text, "a");
+        assert_eq!(state.todo_repo.counters(), (0, 1, 1));
+    }
+}
+
+#[tokio::test]
+async fn test_reorder_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        todo_repo.create("a").unwrap();
+        todo_repo.create("b").unwrap();
+        id = todo_repo.create("c").unwrap().id;
+    }
+
+    let app = app(shared_state);
+
+    // Act
+    let request = Request::patch(format!("/todo/{id}/position"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("position=0"))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let request = Request::get("/todo?filter=All&sort=manual")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+
+    let texts = document
+        .select(&list_selector)
+        .map(|item| item.text().collect::<String>().trim().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts, ["c", "a", "b"]);
+}
+
+#[tokio::test]
+async fn test_roll_recurring_publishes_created_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let mut events = shared_state.read().unwrap().events.subscribe();
+
+    {
+        let state = &mut shared_state.write().unwrap();
+        let todo = state.todo_repo.create("water the plants").unwrap();
+
+        state
+            .todo_repo
+            .update(
+                &todo.id,
+                TodoUpdate {
+                    is_completed: Some(true),
+                    recurrence: Some(Some(Recurrence::Weekly)),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+    }
+
+    // Act
+    let rolled = roll_recurring(
+        &shared_state,
+        SystemTime::now() + Duration::from_secs(8 * 24 * 60 * 60),
+    );
+
+    // Assert
+    assert_eq!(rolled.len(), 1);
+    assert_eq!(events.try_recv().unwrap(), TodoEvent::Created(rolled[0].id));
+
+    let state = shared_state.read().unwrap();
+
+    assert_eq!(state.todo_repo.counters(), (1, 1, 2));
+    assert_eq!(
+        state.todo_repo.toggle_action(&TodoListFilter::All),
+        TodoToggleAction::Check
+    );
+}
+
+#[tokio::test]
+async fn test_purge_old_completed() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let mut events = shared_state.read().unwrap().events.subscribe();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let id = todo_repo.create("a").unwrap().id;
+        todo_repo.set_completed(&id, true).unwrap();
+        todo_repo.create("b").unwrap();
+    }
+
+    let later = SystemTime::now() + Duration::from_secs(3 * 24 * 60 * 60);
+
+    // Act
+    let purged_unset = purge_old_completed(&shared_state, later);
+
+    shared_state.write().unwrap().purge_completed_after =
+        Some(Duration::from_secs(2 * 24 * 60 * 60));
+    let purged = purge_old_completed(&shared_state, later);
+
+    // Assert
+    assert_eq!(purged_unset, 0);
+    assert_eq!(purged, 1);
+    assert_eq!(events.try_recv().unwrap(), TodoEvent::DeletedCompleted);
+    assert_eq!(shared_state.read().unwrap().todo_repo.counters(), (0, 1, 1));
+}
+
+#[tokio::test]
+async fn test_periodic_tasks_skipped_when_read_only() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let state = &mut shared_state.write().unwrap();
+        let id = state.todo_repo.create("water the plants").unwrap().id;
+        state
+            .todo_repo
+            .update(
+                &id,
+                TodoUpdate {
+                    is_completed: Some(true),
+                    recurrence: Some(Some(Recurrence::Weekly)),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        state.purge_completed_after = Some(Duration::from_secs(2 * 24 * 60 * 60));
+        state.read_only.store(true, Ordering::Relaxed);
+    }
+
+    let later = SystemTime::now() + Duration::from_secs(8 * 24 * 60 * 60);
+
+    // Act
+    let rolled = roll_recurring(&shared_state, later);
+    let purged = purge_old_completed(&shared_state, later);
+
+    // Assert
+    assert!(rolled.is_empty());
+    assert_eq!(purged, 0);
+    assert_eq!(shared_state.read().unwrap().todo_repo.counters(), (1, 0, 1));
+}
+
+#[tokio::test]
+async fn test_list_todos_not_modified() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let app = app(shared_state.clone());
+
+    let request = Request::get("/todo?filter=All")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let etag = response.headers().get("ETag").unwrap().clone();
+
+    // Act
+    let request = Request::get("/todo?filter=All")
+        .header("If-None-Match", etag.clone())
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers().get("ETag"), Some(&etag));
+    assert!(parse_response_body(response).await.is_empty());
+
+    shared_state.write().unwrap().todo_repo.create("b").unwrap();
+
+    let request = Request::get("/todo?filter=All")
+        .header("If-None-Match", etag.clone())
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers().get("ETag"), Some(&etag));
+}
+
+#[tokio::test]
+async fn test_list_todos_etag_covers_query() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let app = app(shared_state);
+    let get = |uri: &'static str, if_none_match: Option<&HeaderValue>| {
+        let mut request = Request::get(uri);
+
+        if let Some(etag) = if_none_match {
+            request = request.header("If-None-Match", etag);
+        }
+
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    let response = get("/todo?filter=All", None).await.unwrap();
+    let etag = response.headers().get("ETag").unwrap().clone();
+
+    // Act
+    let same = get("/todo?filter=All&q=+", Some(&etag)).await.unwrap();
+    let tagged = get("/todo?filter=All&tag=work", Some(&etag)).await.unwrap();
+    let sorted = get("/todo?filter=All&sort=Oldest", Some(&etag))
+        .await
+        .unwrap();
+    let searched = get("/todo?filter=All&q=b", Some(&etag)).await.unwrap();
+    let today = get("/todo?filter=Today", None).await.unwrap();
+
+    // Assert
+    assert_eq!(same.status(), StatusCode::NOT_MODIFIED);
+
+    for response in [tagged, sorted, searched] {
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers().get("ETag"), Some(&etag));
+    }
+
+    assert_eq!(today.status(), StatusCode::OK);
+    assert_eq!(today.headers().get("ETag"), None);
+}
+
+#[tokio::test]
+async fn test_todo_version() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let app = app(shared_state);
+
+    let request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=a"))
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap();
+
+    let request = Request::get("/todo/version").body(Body::empty()).unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let version: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(version["version"], 1);
+}
+
+#[tokio::test]
+async fn test_create_todos_batch() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let app = app(shared_state);
+
+    let request = Request::post("/todo/batch")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("lines=milk%0A%0Aeggs"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+
+    assert_eq!(document.select(&list_selector).count(), 2);
+    assert_eq!(local_state.read().unwrap().todo_repo.counters(), (0, 2, 2));
+}
+
+#[tokio::test]
+async fn test_create_todos_batch_json() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let app = app(shared_state);
+
+    let request = Request::post("/todo/batch")
+        .header("Content-Type", "application/json")
+        .body(Body::from(r#"["milk", " ", "eggs"]"#))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let texts = local_state
+        .read()
+        .unwrap()
+        .todo_repo
+        .list(&TodoListFilter::All)
+        .into_iter()
+        .map(|todo| todo.text)
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts, ["eggs", "milk"]);
+}
+
+#[tokio::test]
+async fn test_create_todo_records_source() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let app = app(shared_state);
+
+    let api_request = Request::post("/todo/batch")
+        .header("Content-Type", "application/json")
+        .body(Body::from(r#"["milk"]"#))
+        .unwrap();
+    let web_request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=eggs"))
+        .unwrap();
+
+    // Act
+    let api_response = app.clone().oneshot(api_request).await.unwrap();
+    let web_response = app.clone().oneshot(web_request).await.unwrap();
+
+    // Assert
+    assert_eq!(api_response.status(), StatusCode::OK);
+    assert_eq!(web_response.status(), StatusCode::OK);
+
+    let sources = local_state
+        .read()
+        .unwrap()
+        .todo_repo
+        .list(&TodoListFilter::All)
+        .into_iter()
+        .map(|todo| (todo.text, todo.source))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        sources,
+        [
+            (String::from("eggs"), TodoSource::Web),
+            (String::from("milk"), TodoSource::Api)
+        ]
+    );
+
+    let id = local_state
+        .read()
+        .unwrap()
+        .todo_repo
+        .list(&TodoListFilter::All)[1]
+        .id;
+    let request = Request::get(format!("/todo/{}", id))
+        .header("Accept", "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let body = parse_response_body(app.clone().oneshot(request).await.unwrap()).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["source"], "Api");
+
+    let request = Request::get(format!("/todo/{}", id))
+        .body(Body::empty())
+        .unwrap();
+    let body = parse_response_body(app.oneshot(request).await.unwrap()).await;
+
+    assert!(!body.contains("Api"));
+}
+
+#[tokio::test]
+async fn test_list_todos_progress() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.create("b").unwrap();
+        todo_repo.set_completed(&todo.id, true).unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=All")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let progress_selector = Selector::parse("#todo-progress").unwrap();
+    let progress = document.select(&progress_selector).next().unwrap();
+
+    assert_eq!(progress.value().attr("value"), Some("50"));
+}
+
+#[tokio::test]
+async fn test_lowercase_filter_and_action_queries() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let app = app(shared_state);
+
+    // Act
+    let list_request = Request::get("/todo?filter=all")
+        .body(Body::empty())
+        .unwrap();
+    let list_response = app.clone().oneshot(list_request).await.unwrap();
+
+    let toggle_request = Request::patch("/todo?action=check")
+        .body(Body::empty())
+        .unwrap();
+    let toggle_response = app.oneshot(toggle_request).await.unwrap();
+
+    // Assert
+    assert_eq!(list_response.status(), StatusCode::OK);
+    assert_eq!(list_response.headers()["x-selected-filter"], "all");
+
+    assert_eq!(toggle_response.status(), StatusCode::OK);
+    assert_eq!(local_state.read().unwrap().todo_repo.counters(), (1, 0, 1));
+}
+
+#[tokio::test]
+async fn test_trash_restore_and_purge() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let (kept, purged);
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        kept = todo_repo.create("a").unwrap().id;
+        purged = todo_repo.create("b").unwrap().id;
+    }
+
+    let app = app(shared_state);
+
+    for id in [kept, purged] {
+        let request = Request::delete(format!("/todo/{id}"))
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap();
+    }
+
+    // Act
+    let trash_request = Request::get("/todo?filter=Trash")
+        .body(Body::empty())
+        .unwrap();
+    let trash_response = app.clone().oneshot(trash_request).await.unwrap();
+
+    let restore_request = Request::post(format!("/todo/{kept}/restore"))
+        .body(Body::empty())
+        .unwrap();
+    let restore_response = app.clone().oneshot(restore_request).await.unwrap();
+
+    let purge_request = Request::delete("/todo/trash").body(Body::empty()).unwrap();
+    let purge_response = app.oneshot(purge_request).await.unwrap();
+
+    // Assert
+    let body = parse_response_body(trash_response).await;
+    let document = Html::parse_document(&body);
+    let restore_selector = Selector::parse("#todo-list .todo-restore").unwrap();
+
+    assert_eq!(document.select(&restore_selector).count(), 2);
+
+    assert_eq!(restore_response.status(), StatusCode::OK);
+    assert_eq!(purge_response.status(), StatusCode::OK);
+
+    let state = local_state.read().unwrap();
+
+    assert!(state.todo_repo.get(&kept).unwrap().deleted_at.is_none());
+    assert!(state.todo_repo.get(&purged).is_err());
+    assert_eq!(state.todo_repo.counters(), (0, 1, 1));
+}
+
+#[tokio::test]
+async fn test_update_trashed_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let id = todo_repo.create("a").unwrap().id;
+        todo_repo.delete(&id).unwrap();
+        id
+    };
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=b"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let trashed = local_state
+        .read()
+        .unwrap()
+        .todo_repo
+        .list(&TodoListFilter::Trash);
+
+    assert_eq!(trashed[0].text, "a");
+}
+
+#[tokio::test]
+async fn test_get_todo_json() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let app = app(shared_state);
+
+    // Act
+    let request = Request::get(format!("/todo/{}", todo.id))
+        .header("Accept", "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    let missing_request = Request::get(format!("/todo/{}", uuid::Uuid::new_v4()))
+        .header("Accept", "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let missing_response = app.oneshot(missing_request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["text"], "a");
+    assert_eq!(json["status"], "Todo");
+
+    assert_eq!(missing_response.status(), StatusCode::NOT_FOUND);
+
+    let body = parse_response_body(missing_response).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["error"], "Todo not found");
+}
+
+#[tokio::test]
+async fn test_get_todo_etag_per_representation() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let app = app(shared_state);
+    let get = |accept: &'static str, if_none_match: Option<HeaderValue>| {
+        let mut request = Request::get(format!("/todo/{}", todo.id)).header("Accept", accept);
+
+        if let Some(etag) = if_none_match {
+            request = request.header("If-None-Match", etag);
+        }
+
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    let html = get("text/html", None).await.unwrap();
+    let html_etag = html.headers().get("ETag").unwrap().clone();
+
+    // Act
+    let json = get("application/json", Some(html_etag.clone()))
+        .await
+        .unwrap();
+    let json_etag = json.headers().get("ETag").unwrap().clone();
+    let json_again = get("application/json", Some(json_etag.clone()))
+        .await
+        .unwrap();
+    let html_again = get("text/html", Some(html_etag.clone())).await.unwrap();
+
+    // Assert
+    assert_eq!(html.headers().get("Vary").unwrap(), "accept");
+    assert_eq!(json.status(), StatusCode::OK);
+    assert_eq!(json.headers().get("Vary").unwrap(), "accept");
+    assert_ne!(json_etag, html_etag);
+    assert_eq!(json_again.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(html_again.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn test_errors_as_json() {
+    // Arrange
+    let app = app(SharedState::default());
+
+    let missing_request = Request::delete(format!("/todo/{}", uuid::Uuid::new_v4()))
+        .header("Accept", "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let malformed_request = Request::patch("/todo/batch/complete")
+        .header("Accept", "application/json")
+        .header("Content-Type", "application/json")
+        .body(Body::from("{"))
+        .unwrap();
+    let html_request = Request::delete(format!("/todo/{}", uuid::Uuid::new_v4()))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let missing_response = app.clone().oneshot(missing_request).await.unwrap();
+    let malformed_response = app.clone().oneshot(malformed_request).await.unwrap();
+    let html_response = app.oneshot(html_request).await.unwrap();
+
+    // Assert
+    assert_eq!(missing_response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        missing_response.headers()["Content-Type"],
+        "application/json"
+    );
+
+    let body = parse_response_body(missing_response).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["error"], "Todo not found");
+    assert_eq!(json["code"], "not_found");
+
+    assert_eq!(malformed_response.status(), StatusCode::BAD_REQUEST);
+
+    let body = parse_response_body(malformed_response).await;
+    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+    assert_eq!(json["code"], "bad_request");
+    assert!(!json["error"].as_str().unwrap().is_empty());
+
+    assert_eq!(html_response.status(), StatusCode::NOT_FOUND);
+    assert!(html_response.headers()["Content-Type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+}
+
+#[tokio::test]
+async fn test_list_todos_render_limit() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let state = &mut shared_state.write().unwrap();
+        state.render_limit = 2;
+
+        for text in ["a", "b", "c"] {
+            state.todo_repo.create(text).unwrap();
+        }
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=All")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let list_selector = Selector::parse("#todo-list .todo-item").unwrap();
+    let notice_selector = Selector::parse("#todo-list .todo-truncated").unwrap();
+
+    assert_eq!(document.select(&list_selector).count(), 2);
+
+    let notice = document.select(&notice_selector).next().unwrap();
+
+    assert_eq!(
+        notice.text().collect::<String>().trim(),
+        "Showing first 2 of 3 todos"
+    );
+}
+
+#[tokio::test]
+async fn test_create_todo_color() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let app = app(shared_state);
+
+    // Act
+    let request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=a&color=red"))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    let invalid_request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=b&color=purple"))
+        .unwrap();
+    let invalid_response = app.oneshot(invalid_request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let color_selector = Selector::parse(".todo-item .todo-color.is-danger").unwrap();
+
+    assert_eq!(document.select(&color_selector).count(), 1);
+
+    assert_eq!(invalid_response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(local_state.read().unwrap().todo_repo.counters(), (0, 1, 1));
+}
+
+#[tokio::test]
+async fn test_update_todo_revision() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let app = app(shared_state);
+
+    // Act
+    let request = Request::patch(format!("/todo/{}", todo.id))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from(format!("text=b&revision={}", todo.revision)))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    let stale_request = Request::patch(format!("/todo/{}", todo.id))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from(format!("text=c&revision={}", todo.revision)))
+        .unwrap();
+    let stale_response = app.oneshot(stale_request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(stale_response.status(), StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_update_todo_out_of_filter_renders_counters() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let key = shared_state.read().unwrap().cookie_key.clone();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        id = todo_repo.create("a").unwrap().id;
+        todo_repo.create("b").unwrap();
+    }
+
+    let mut jar = CookieJar::new();
+    jar.signed_mut(&key.0)
+        .add(Cookie::new("todo_filter", "active"));
+    let cookie = jar.get("todo_filter").unwrap();
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Cookie", cookie.stripped().to_string())
+        .body(Body::from("is_completed=true"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+
+    let item_selector = Selector::parse(".todo-item").unwrap();
+    let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+
+    assert_eq!(document.select(&item_selector).count(), 0);
+    assert_eq!(document.select(&toggle_selector).count(), 1);
+
+    for (name, expected) in [("all", "2"), ("active", "1"), ("completed", "1")] {
+        let counter_selector = Selector::parse(&format!("#todo-counter-{name}")).unwrap();
+        let counter = document.select(&counter_selector).next().unwrap();
+
+        assert_eq!(counter.text().collect::<String>().trim(), expected);
+    }
+}
+
+#[tokio::test]
+async fn test_update_todo_start() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let id = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("a")
+        .unwrap()
+        .id;
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{id}"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("status=InProgress"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let in_progress_selector = Selector::parse(".todo-item .todo-in-progress").unwrap();
+    let start_selector = Selector::parse(".todo-item .todo-start").unwrap();
+
+    assert_eq!(document.select(&in_progress_selector).count(), 1);
+    assert_eq!(document.select(&start_selector).count(), 0);
+
+    let state = local_state.read().unwrap();
+
+    assert_eq!(
+        state.todo_repo.get(&id).unwrap().status,
+        TodoStatus::InProgress
+    );
+    assert_eq!(state.todo_repo.num_in_progress_items, 1);
+}
+
+#[tokio::test]
+async fn test_update_todo_triggers_when_leaving_filter() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let key = shared_state.read().unwrap().cookie_key.clone();
+    let id;
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        id = todo_repo.create("a").unwrap().id;
+    }
+
+    let mut jar = CookieJar::new();
+    jar.signed_mut(&key.0)
+        .add(Cookie::new("todo_filter", "active"));
+    let cookie = jar.get("todo_filter").unwrap().stripped().to_string();
+
+    for (body, expected) in [
+        ("text=b", None),
+        ("is_completed=true", Some("todo-left-filter")),
+    ] {
+        let request = Request::patch(format!("/todo/{id}"))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Cookie", &cookie)
+            .body(Body::from(body))
+            .unwrap();
+
+        // Act
+        let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response
+                .headers()
+                .get("HX-Trigger")
+                .map(|value| value.to_str().unwrap()),
+            expected,
+            "{body}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_update_todo_triggers_on_completion() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let id;
+
+    {
+        let mut state = shared_state.write().unwrap();
+        state.completion_trigger = true;
+        id = state.todo_repo.create("a").unwrap().id;
+    }
+
+    for (body, expected) in [
+        ("text=b", None),
+        ("is_completed=true", Some("todo-completed")),
+        ("is_completed=true", None),
+        ("is_completed=false", None),
+    ] {
+        let request = Request::patch(format!("/todo/{id}"))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from(body))
+            .unwrap();
+
+        // Act
+        let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response
+                .headers()
+                .get("HX-Trigger")
+                .map(|value| value.to_str().unwrap()),
+            expected,
+            "{body}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_compress_large_list() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        for i in 0..100 {
+            todo_repo.create(&format!("todo {i}")).unwrap();
+        }
+    }
+
+    let list_request = Request::get("/todo")
+        .header("Accept-Encoding", "gzip")
+        .body(Body::empty())
+        .unwrap();
+    let events_request = Request::get("/todo/events")
+        .header("Accept-Encoding", "gzip")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let list_response = app(shared_state.clone())
+        .oneshot(list_request)
+        .await
+        .unwrap();
+    let events_response = app(shared_state).oneshot(events_request).await.unwrap();
+
+    // Assert
+    assert_eq!(list_response.status(), StatusCode::OK);
+    assert_eq!(list_response.headers()["Content-Encoding"], "gzip");
+
+    assert_eq!(events_response.status(), StatusCode::OK);
+    assert_eq!(
+        events_response.headers()["Content-Type"],
+        "text/event-stream"
+    );
+    assert!(events_response.headers().get("Content-Encoding").is_none());
+}
+
+#[tokio::test]
+async fn test_list_todos_search_and_completed() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let id = todo_repo.create("Buy milk").unwrap().id;
+        todo_repo.set_completed(&id, true).unwrap();
+        todo_repo.create("buy more milk").unwrap();
+        todo_repo.create("Call mom").unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=all&q=MILK&completed=false")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let text_selector = Selector::parse(".todo-item p.is-flex-grow-1").unwrap();
+    let texts = document
+        .select(&text_selector)
+        .map(|text| text.text().collect::<String>().trim().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts, ["buy more milk"]);
+}
+
+#[tokio::test]
+async fn test_list_todos_search_too_long() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = |q: &str, fuzzy: bool| {
+        Request::get(format!("/todo?filter=all&q={}&fuzzy={}", q, fuzzy))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let longest = "a".repeat(MAX_TEXT_LEN);
+    let too_long = "a".repeat(MAX_TEXT_LEN + 1);
+
+    // Act
+    let longest_exact = app.clone().oneshot(request(&longest, false)).await.unwrap();
+    let longest_fuzzy = app.clone().oneshot(request(&longest, true)).await.unwrap();
+    let too_long_exact = app
+        .clone()
+        .oneshot(request(&too_long, false))
+        .await
+        .unwrap();
+    let too_long_fuzzy = app.oneshot(request(&too_long, true)).await.unwrap();
+
+    // Assert
+    assert_eq!(longest_exact.status(), StatusCode::OK);
+    assert_eq!(longest_fuzzy.status(), StatusCode::OK);
+    assert_eq!(too_long_exact.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(too_long_fuzzy.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_list_todos_fuzzy_search() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        todo_repo.create("Buy milk").unwrap();
+        todo_repo.create("Call mom").unwrap();
+    }
+
+    let app = app(shared_state);
+    let texts = |body: String| {
+        let document = Html::parse_document(&body);
+        let text_selector = Selector::parse(".todo-item p.is-flex-grow-1").unwrap();
+        document
+            .select(&text_selector)
+            .map(|text| text.text().collect::<String>().trim().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // Act
+    let exact = app
+        .clone()
+        .oneshot(
+            Request::get("/todo?filter=all&q=mylk")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let fuzzy = app
+        .oneshot(
+            Request::get("/todo?filter=all&q=mylk&fuzzy=true")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(exact.status(), StatusCode::OK);
+    assert_eq!(fuzzy.status(), StatusCode::OK);
+    assert!(texts(parse_response_body(exact).await).is_empty());
+    assert_eq!(texts(parse_response_body(fuzzy).await), ["Buy milk"]);
+}
+
+#[tokio::test]
+async fn test_list_todos_has_due() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let id = todo_repo.create("scheduled").unwrap().id;
+        todo_repo
+            .update(
+                &id,
+                TodoUpdate {
+                    due_at: Some(Some(SystemTime::now() + Duration::from_secs(60))),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        todo_repo.create("unscheduled").unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=all&has_due=true")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let text_selector = Selector::parse(".todo-item p.is-flex-grow-1").unwrap();
+    let texts = document
+        .select(&text_selector)
+        .map(|text| text.text().collect::<String>().trim().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts, ["scheduled"]);
+}
+
+#[tokio::test]
+async fn test_list_todos_overdue() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let id = todo_repo.create("late").unwrap().id;
+        todo_repo
+            .update(
+                &id,
+                TodoUpdate {
+                    due_at: Some(Some(SystemTime::now() - Duration::from_secs(60))),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        todo_repo.create("undated").unwrap();
+    }
+
+    let app = app(shared_state);
+    let request = Request::get("/todo?filter=overdue")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let item_selector = Selector::parse(".todo-item").unwrap();
+    let overdue_selector = Selector::parse(".todo-item.todo-overdue").unwrap();
+
+    assert_eq!(document.select(&item_selector).count(), 1);
+    assert_eq!(document.select(&overdue_selector).count(), 1);
+    assert!(body.contains("late"));
+}
+
+#[tokio::test]
+async fn test_list_todos_default_filter() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let state = &mut shared_state.write().unwrap();
+        let todo = state.todo_repo.create("a").unwrap();
+
+        state.todo_repo.create("b").unwrap();
+        state.todo_repo.set_completed(&todo.id, true).unwrap();
+        state.default_filter = TodoListFilter::Active;
+    }
+
+    let app = app(shared_state);
+
+    // Act
+    let request = Request::get("/todo").body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+
+    let index_request = Request::get("/").body(Body::empty()).unwrap();
+    let index_response = app.oneshot(index_request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-selected-filter"], "active");
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+
+    let texts = document
+        .select(&list_selector)
+        .map(|item| item.text().collect::<String>().trim().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts, ["b"]);
+
+    let body = parse_response_body(index_response).await;
+    let document = Html::parse_document(&body);
+    let active_tab_selector = Selector::parse(".panel-tabs a.is-active").unwrap();
+
+    let active_tabs = document
+        .select(&active_tab_selector)
+        .map(|tab| tab.value().id().unwrap())
+        .collect::<Vec<_>>();
+    let texts = document
+        .select(&list_selector)
+        .map(|item| item.text().collect::<String>().trim().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(active_tabs, ["todo-list-active"]);
+    assert_eq!(texts, ["b"]);
+}
+
+#[tokio::test]
+async fn test_list_todos_without_query_uses_selected_filter() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.create("b").unwrap();
+        todo_repo.set_completed(&todo.id, true).unwrap();
+    }
+
+    let request = Request::get("/todo?filter=completed")
+        .body(Body::empty())
+        .unwrap();
+    let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+    let cookie = Cookie::parse(
+        response.headers()["Set-Cookie"]
+            .to_str()
+            .unwrap()
+            .to_string(),
+    )
+    .unwrap();
+
+    let request = Request::get("/todo")
+        .header("Cookie", format!("{}={}", cookie.name(), cookie.value()))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["x-selected-filter"], "completed");
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+
+    let texts = document
+        .select(&list_selector)
+        .map(|item| item.text().collect::<String>().trim().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts, ["a"]);
+}
+
+#[tokio::test]
+async fn test_todo_socket_create() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app(shared_state).into_make_service()),
+    );
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/todo/ws", addr))
+        .await
+        .unwrap();
+
+    // Act
+    socket
+        .send(Message::Text(String::from(
+            r#"{"type": "create", "text": "a"}"#,
+        )))
+        .await
+        .unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+    // Assert
+    let event: WsEvent = serde_json::from_str(message.to_text().unwrap()).unwrap();
+    let items = local_state
+        .read()
+        .unwrap()
+        .todo_repo
+        .list(&TodoListFilter::All);
+
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].text, "a");
+    assert_eq!(event, WsEvent::Created { id: items[0].id });
+}
+
+#[tokio::test]
+async fn test_todo_socket_create_duplicate() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().todo_repo.create("a").unwrap();
+    let local_state = shared_state.clone();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app(shared_state).into_make_service()),
+    );
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/todo/ws", addr))
+        .await
+        .unwrap();
+
+    // Act
+    socket
+        .send(Message::Text(String::from(
+            r#"{"type": "create", "text": " a "}"#,
+        )))
+        .await
+        .unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+    // Assert
+    let event: WsEvent = serde_json::from_str(message.to_text().unwrap()).unwrap();
+
+    assert_eq!(
+        event,
+        WsEvent::Error {
+            error: String::from("An active todo with this text already exists")
+        }
+    );
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 1);
+}
+
+#[tokio::test]
+async fn test_todo_socket_rate_limit() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().write_limiter =
+        Some(RateLimiter::new(1, Duration::from_secs(60)));
+    let local_state = shared_state.clone();
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app(shared_state).into_make_service_with_connect_info::<std::net::SocketAddr>()),
+    );
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/todo/ws", addr))
+        .await
+        .unwrap();
+
+    // Act
+    let mut events = Vec::new();
+
+    for text in ["a", "b"] {
+        socket
+            .send(Message::Text(format!(
+                r#"{{"type": "create", "text": "{text}"}}"#
+            )))
+            .await
+            .unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+
+        events.push(serde_json::from_str::<WsEvent>(message.to_text().unwrap()).unwrap());
+    }
+
+    // Assert
+    assert!(matches!(events[0], WsEvent::Created { .. }));
+    assert_eq!(
+        events[1],
+        WsEvent::Error {
+            error: String::from("Too many writes, try again later")
+        }
+    );
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 1);
+}
+
+#[tokio::test]
+async fn test_todo_socket_invalid_command() {
+    // Arrange
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(
+        axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app(SharedState::default()).into_make_service()),
+    );
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/todo/ws", addr))
+        .await
+        .unwrap();
+
+    // Act
+    socket
+        .send(Message::Text(format!(
+            r#"{{"type": "toggle", "id": "{}"}}"#,
+            uuid::Uuid::new_v4()
+        )))
+        .await
+        .unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+    // Assert
+    let event: WsEvent = serde_json::from_str(message.to_text().unwrap()).unwrap();
+
+    assert_eq!(
+        event,
+        WsEvent::Error {
+            error: String::from("Todo not found")
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_list_todos_cursor_pages() {
+    // Arrange
+    let shared_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        for text in ["a", "b", "c", "d", "e"] {
+            todo_repo.create(text).unwrap();
+        }
+    }
+
+    let list_texts = |body: String| {
+        let document = Html::parse_document(&body);
+        let list_selector = Selector::parse("#todo-list .todo-item p").unwrap();
+
+        document
+            .select(&list_selector)
+            .map(|item| item.text().collect::<String>().trim().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    // Act
+    let request = Request::get("/todo?filter=all&limit=3")
+        .body(Body::empty())
+        .unwrap();
+    let first = app(shared_state.clone()).oneshot(request).await.unwrap();
+    let cursor = first.headers()["x-next-cursor"]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let request = Request::get(format!("/todo?filter=all&limit=3&after={}", cursor))
+        .body(Body::empty())
+        .unwrap();
+    let second = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(second.status(), StatusCode::OK);
+    assert!(second.headers().get("x-next-cursor").is_none());
+
+    let first = list_texts(parse_response_body(first).await);
+    let second = list_texts(parse_response_body(second).await);
+
+    assert_eq!(first.len(), 3);
+    assert_eq!(second.len(), 2);
+
+    let mut seen = [first, second].concat();
+    seen.sort();
+
+    assert_eq!(seen, ["a", "b", "c", "d", "e"]);
+}
+
+#[tokio::test]
+async fn test_list_todos_invalid_cursor() {
+    // Arrange
+    let app = app(SharedState::default());
+    let request = Request::get("/todo?after=yesterday")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_todo_text_is_escaped() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let text = "<script>alert(1)</script>";
+
+    let (active, completed) = {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let active = todo_repo.create(text).unwrap();
+        let completed = todo_repo.create(text).unwrap();
+
+        todo_repo.set_completed(&completed.id, true).unwrap();
+        (active, completed)
+    };
+
+    let requests = [
+        String::from("/todo?filter=all"),
+        format!("/todo/{}", active.id),
+        format!("/todo/{}", completed.id),
+    ];
+
+    for uri in requests {
+        let request = Request::get(&uri).body(Body::empty()).unwrap();
+
+        // Act
+        let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = parse_response_body(response).await;
+
+        assert!(body.contains("&lt;script&gt;"), "{} is not escaped", uri);
+        assert!(!body.contains(text), "{} renders a live tag", uri);
+    }
+}
+
+#[tokio::test]
+async fn test_cors_preflight() {
+    // Arrange
+    let shared_state = SharedState::default();
+    shared_state.write().unwrap().cors_origins = vec!["https://app.example.com".parse().unwrap()];
+
+    let preflight = |origin: &str| {
+        Request::options("/todo")
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Act
+    let allowed = app(shared_state.clone())
+        .oneshot(preflight("https://app.example.com"))
+        .await
+        .unwrap();
+    let denied = app(shared_state)
+        .oneshot(preflight("https://evil.example.com"))
+        .await
+        .unwrap();
+    let unconfigured = app(SharedState::default())
+        .oneshot(preflight("https://app.example.com"))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(
+        allowed.headers()["Access-Control-Allow-Origin"],
+        "https://app.example.com"
+    );
+    assert!(allowed.headers()["Access-Control-Allow-Methods"]
+        .to_str()
+        .unwrap()
+        .contains("POST"));
+    assert!(denied
+        .headers()
+        .get("Access-Control-Allow-Origin")
+        .is_none());
+    assert!(unconfigured
+        .headers()
+        .get("Access-Control-Allow-Origin")
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_create_todo_body_too_large() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    shared_state.write().unwrap().body_limit = 1024;
+
+    let app = app(shared_state);
+    let request = Request::post("/todo")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from(format!("text={}", "a".repeat(2048))))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(local_state.read().unwrap().todo_repo.num_all_items, 0);
+}
+
+#[tokio::test]
+async fn test_list_todos_toggle_label_follows_listed_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let completed_state = SharedState::default();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.create("b").unwrap();
+        todo_repo.set_completed(&todo.id, true).unwrap();
+    }
+
+    {
+        let todo_repo = &mut completed_state.write().unwrap().todo_repo;
+
+        todo_repo.create("a").unwrap();
+        todo_repo.create("b").unwrap();
+        todo_repo.toggle_completed(&TodoToggleAction::Check, &TodoListFilter::All);
+    }
+
+    let cases = [
+        (completed_state, "/todo?filter=all", "Uncheck all"),
+        (shared_state.clone(), "/todo?filter=all", "Check all"),
+        (
+            shared_state.clone(),
+            "/todo?filter=completed",
+            "Uncheck all",
+        ),
+        (shared_state.clone(), "/todo?filter=active", "Check all"),
+        (shared_state, "/todo?filter=trash", "Check all"),
+    ];
+
+    for (state, uri, label) in cases {
+        let request = Request::get(uri).body(Body::empty()).unwrap();
+
+        // Act
+        let response = app(state).oneshot(request).await.unwrap();
+
+        // Assert
+        let body = parse_response_body(response).await;
+        let document = Html::parse_document(&body);
+        let toggle_selector = Selector::parse("#todo-toggle-completed").unwrap();
+
+        assert_eq!(
+            document
+                .select(&toggle_selector)
+                .next()
+                .unwrap()
+                .inner_html(),
+            label,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_todo_repo_error_statuses() {
+    let variants = [
+        TodoRepoError::NotFound,
+        TodoRepoError::Duplicate,
+        TodoRepoError::CapacityReached,
+        TodoRepoError::InvalidColor,
+        TodoRepoError::Conflict,
+        TodoRepoError::BlankText,
+        TodoRepoError::TextTooLong,
+        TodoRepoError::InvalidTag,
+    ];
+
+    for variant in variants {
+        // Arrange
+        let shared_state = SharedState::default();
+
+        if variant == TodoRepoError::CapacityReached {
+            shared_state.write().unwrap().todo_repo = TodoRepo::with_capacity(1);
+        }
+
+        let id = shared_state
+            .write()
+            .unwrap()
+            .todo_repo
+            .create("a")
+            .unwrap()
+            .id;
+        let form = |request: axum::http::request::Builder, body: &str| {
+            request
+                .header("Content-Type", "application/x-www-form-urlencoded")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        // Matching without a wildcard keeps this test in step with the error type.
+        let (request, status) = match variant {
+            TodoRepoError::NotFound => (
+                form(
+                    Request::patch(format!("/todo/{}", uuid::Uuid::new_v4())),
+                    "text=b",
+                ),
+                StatusCode::NOT_FOUND,
+            ),
+            TodoRepoError::Duplicate => {
+                (form(Request::post("/todo"), "text=a"), StatusCode::CONFLICT)
+            }
+            TodoRepoError::CapacityReached => (
+                form(Request::post("/todo"), "text=b"),
+                StatusCode::INSUFFICIENT_STORAGE,
+            ),
+            TodoRepoError::InvalidColor => (
+                form(Request::patch(format!("/todo/{}", id)), "color=purple"),
+                StatusCode::BAD_REQUEST,
+            ),
+            TodoRepoError::Conflict => (
+                form(
+                    Request::patch(format!("/todo/{}", id)),
+                    "text=b&revision=99",
+                ),
+                StatusCode::CONFLICT,
+            ),
+            TodoRepoError::BlankText => (
+                Request::post("/todo/transaction")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!([{ "op": "update", "id": id, "text": " " }]).to_string(),
+                    ))
+                    .unwrap(),
+                StatusCode::BAD_REQUEST,
+            ),
+            TodoRepoError::TextTooLong => (
+                form(
+                    Request::patch(format!("/todo/{}/append", id)),
+                    &format!("text={}", "b".repeat(MAX_TEXT_LEN)),
+                ),
+                StatusCode::BAD_REQUEST,
+            ),
+            TodoRepoError::InvalidTag => (
+                Request::post("/todo/tags/add")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(
+                        serde_json::json!({ "ids": [id], "tag": " a,b " }).to_string(),
+                    ))
+                    .unwrap(),
+                StatusCode::BAD_REQUEST,
+            ),
+        };
+
+        // Act
+        let response = app(shared_state).oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), status, "{:?}", variant);
+    }
+}
+
+#[tokio::test]
+async fn test_preview_completed_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        for text in ["a", "b", "c"] {
+            let todo = todo_repo.create(text).unwrap();
+
+            if text != "b" {
+                todo_repo.set_completed(&todo.id, true).unwrap();
+            }
+        }
+    }
+
+    let version = local_state.read().unwrap().todo_repo.version();
+    let request = Request::get("/todo/completed/preview")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let item_selector = Selector::parse(".todo-preview-item").unwrap();
+    let button_selector = Selector::parse("button, input").unwrap();
+
+    let texts = document
+        .select(&item_selector)
+        .map(|item| item.inner_html())
+        .collect::<Vec<_>>();
+
+    assert_eq!(texts, ["c", "a"]);
+    assert_eq!(document.select(&button_selector).count(), 0);
+
+    let state = local_state.read().unwrap();
+
+    assert_eq!(state.todo_repo.counters(), (2, 1, 3));
+    assert_eq!(state.todo_repo.version(), version);
+}
+
+#[tokio::test]
+async fn test_reset_completed_todos() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+
+    {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+
+        for text in ["a", "b", "c"] {
+            let todo = todo_repo.create(text).unwrap();
+
+            if text != "b" {
+                todo_repo.set_completed(&todo.id, true).unwrap();
+            }
+        }
+    }
+
+    let request = Request::patch("/todo/completed/reset")
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let item_selector = Selector::parse(".todo-item input").unwrap();
+
+    assert_eq!(document.select(&item_selector).count(), 3);
+    assert_eq!(
+        document
+            .select(&item_selector)
+            .filter(|e| e.value().attr("checked").is_some())
+            .count(),
+        0
+    );
+
+    assert_eq!(local_state.read().unwrap().todo_repo.counters(), (0, 3, 3));
+}
+
+#[tokio::test]
+async fn test_duplicate_todo() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let todo = {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        let todo = todo_repo.create("a").unwrap();
+
+        todo_repo.set_completed(&todo.id, true).unwrap();
+        todo
+    };
+
+    let request = Request::post(format!("/todo/{}/duplicate", todo.id))
+        .body(Body::empty())
+        .unwrap();
+
+    // Act
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let checkbox_selector = Selector::parse(".todo-item input[type=checkbox]").unwrap();
+    let checkbox = document.select(&checkbox_selector).next().unwrap();
+
+    assert_ne!(
+        checkbox.value().id(),
+        Some(&*format!("todo-done-{}", todo.id))
+    );
+    assert!(checkbox.value().attr("checked").is_none());
+
+    let state = local_state.read().unwrap();
+    let copies = state.todo_repo.list(&TodoListFilter::Active);
+
+    assert_eq!(copies.len(), 1);
+    assert_ne!(copies[0].id, todo.id);
+    assert_eq!(copies[0].text, "a");
+    assert_eq!(state.todo_repo.counters(), (1, 1, 2));
+}
+
+#[tokio::test]
+async fn test_append_todo_text() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let local_state = shared_state.clone();
+    let todo = shared_state
+        .write()
+        .unwrap()
+        .todo_repo
+        .create("write report")
+        .unwrap();
+
+    let app = app(shared_state);
+    let request = Request::patch(format!("/todo/{}/append", todo.id))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=outline+done"))
+        .unwrap();
+
+    // Act
+    let response = app.oneshot(request).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = parse_response_body(response).await;
+    let document = Html::parse_document(&body);
+    let item_selector = Selector::parse(".todo-item p").unwrap();
+
+    assert_eq!(
+        document.select(&item_selector).next().unwrap().inner_html(),
+        "write report; outline done"
+    );
+    assert_eq!(
+        local_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .get(&todo.id)
+            .unwrap()
+            .text,
+        "write report; outline done"
+    );
+}
+
+#[tokio::test]
+async fn test_edit_todo_by_short_id() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let todo = {
+        let todo_repo = &mut shared_state.write().unwrap().todo_repo;
+        todo_repo.create("a").unwrap();
+        todo_repo.create("b").unwrap()
+    };
+
+    for uri in [
+        format!("/todo/{}", todo.id),
+        format!("/t/{}", todo.short_id()),
+    ] {
+        let request = Request::get(&uri)
+            .header("Accept", "application/json")
+            .body(Body::empty())
+            .unwrap();
+
+        // Act
+        let response = app(shared_state.clone()).oneshot(request).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK, "{uri}");
+
+        let body: Todo = serde_json::from_str(&parse_response_body(response).await).unwrap();
+
+        assert_eq!(body.id, todo.id, "{uri}");
+    }
+
+    let request = Request::get("/t/zz").body(Body::empty()).unwrap();
+    let response = app(shared_state).oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_short_id_writes_read_only() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+    shared_state
+        .read()
+        .unwrap()
+        .read_only
+        .store(true, Ordering::Relaxed);
+
+    let uri = format!("/t/{}", todo.short_id());
+    let update = Request::patch(&uri)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(Body::from("text=b"))
+        .unwrap();
+    let delete = Request::delete(&uri).body(Body::empty()).unwrap();
+
+    // Act
+    let updated = app(shared_state.clone()).oneshot(update).await.unwrap();
+    let deleted = app(shared_state.clone()).oneshot(delete).await.unwrap();
+
+    // Assert
+    assert_eq!(updated.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(deleted.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        shared_state
+            .read()
+            .unwrap()
+            .todo_repo
+            .get(&todo.id)
+            .unwrap()
+            .text,
+        "a"
+    );
+}
+
+#[tokio::test]
+async fn test_short_id_writes_rate_limited() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+    shared_state.write().unwrap().write_limiter =
+        Some(RateLimiter::new(1, Duration::from_secs(60)));
+
+    let uri = format!("/t/{}", todo.short_id());
+    let update = || {
+        Request::patch(&uri)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(Body::from("text=b"))
+            .unwrap()
+    };
+
+    // Act
+    let allowed = app(shared_state.clone()).oneshot(update()).await.unwrap();
+    let limited_update = app(shared_state.clone()).oneshot(update()).await.unwrap();
+    let limited_delete = app(shared_state.clone())
+        .oneshot(Request::delete(&uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(allowed.status(), StatusCode::OK);
+    assert_eq!(limited_update.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(limited_delete.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(shared_state.read().unwrap().todo_repo.num_all_items, 1);
+}
+
+#[tokio::test]
+async fn test_edit_todo_if_modified_since() {
+    // Arrange
+    let shared_state = SharedState::default();
+    let todo = shared_state.write().unwrap().todo_repo.create("a").unwrap();
+
+    let request = |if_modified_since: Option<&str>| {
+        let request =
+            Request::get(format!("/todo/{}", todo.id)).header("Accept", "application/json");
+
+        match if_modified_since {
+            Some(if_modified_since) => request.header("If-Modified-Since", if_modified_since),
+            None => request,
+        }
+        .body(Body::empty())
+        .unwrap()
+    };
+
+    let response = app(shared_state.clone())
+        .oneshot(request(None))
+        .await
+        .unwrap();
+    let last_modified = response.headers()["Last-Modified"]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    // Act
+    let unchanged = app(shared_state.clone())
+        .oneshot(request(Some(&last_modified)))
+        .await
+        .unwrap();
+    let stale = app(shared_state)
+        .oneshot(request(Some("Thu, 01 Jan 1970 00:00:00 GMT")))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(unchanged.headers()["Last-Modified"], last_modified.as_str());
+    assert_eq!(stale.status(), StatusCode::OK);
 }